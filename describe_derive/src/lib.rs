@@ -0,0 +1,438 @@
+use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+// #[derive(Describe)] 的实现 用来演示派生宏是怎么工作的
+// 派生宏就是一个函数: 输入是结构体/枚举定义的TokenStream 输出是要追加到代码中的TokenStream
+// 实际项目一般用syn解析、用quote生成代码 这里不用它们 手动遍历token 再把生成的代码拼成字符串解析回TokenStream
+// 内部使用proc_macro2的token类型 标准库的proc_macro只能在宏展开时使用 proc_macro2在普通的单元测试中也能构造token
+//
+// 为结构体生成:
+//   const FIELDS: &[(&str, &str)]     每个字段的名字和类型
+//   fn describe(&self) -> String      例如 Circle { x: f64 = 0.0, y: f64 = 0.0, radius: f64 = 1.0 }
+// 为枚举生成:
+//   const VARIANTS: &[&str]           所有变体的名字
+//   fn variant_name(&self) -> &str    当前值的变体名
+//   fn describe(&self) -> String      例如 Message::Move { x: i32 = 1, y: i32 = 2 }
+// 两者都会生成 const DERIVE_EXPANSION: &str 内容是宏生成的代码本身 可以打印出来观察展开结果
+// 字段的值用 {:?} 输出 所以所有字段的类型都需要实现Debug
+
+enum Fields {
+    // struct A { x: T }  (字段名, 类型)
+    Named(Vec<(String, String)>),
+    // struct A(T, U)
+    Unnamed(Vec<String>),
+    // struct A;
+    Unit,
+}
+
+struct Variant {
+    name: String,
+    fields: Fields,
+}
+
+enum Item {
+    Struct { name: String, fields: Fields },
+    Enum { name: String, variants: Vec<Variant> },
+}
+
+#[proc_macro_derive(Describe)]
+pub fn derive_describe(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    describe(input.into()).into()
+}
+
+fn describe(input: TokenStream) -> TokenStream {
+    // 出错时生成一个compile_error! 编译器会把它当作普通的编译错误报告出来
+    match parse_item(input) {
+        Ok(item) => expand(&item)
+            .parse()
+            .unwrap_or_else(|err| compile_error(&format!("Describe 生成的代码无法解析: {}", err))),
+        Err(message) => compile_error(&message),
+    }
+}
+
+// compile_error!("message"); 直接构造token 不需要再解析字符串
+fn compile_error(message: &str) -> TokenStream {
+    let args = TokenStream::from(TokenTree::Literal(Literal::string(message)));
+    [
+        TokenTree::Ident(Ident::new("compile_error", Span::call_site())),
+        TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+        TokenTree::Group(Group::new(Delimiter::Parenthesis, args)),
+        TokenTree::Punct(Punct::new(';', Spacing::Alone)),
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn is_punct(tt: &TokenTree, ch: char) -> bool {
+    matches!(tt, TokenTree::Punct(p) if p.as_char() == ch)
+}
+
+fn is_ident(tt: &TokenTree, name: &str) -> bool {
+    matches!(tt, TokenTree::Ident(i) if i == name)
+}
+
+// 跳过开头的属性 #[...] 和可见性 pub / pub(crate)
+fn skip_attrs_and_vis(tokens: &[TokenTree]) -> &[TokenTree] {
+    let mut rest = tokens;
+    loop {
+        match rest {
+            [hash, TokenTree::Group(g), tail @ ..] if is_punct(hash, '#') && g.delimiter() == Delimiter::Bracket => {
+                rest = tail;
+            }
+            [vis, TokenTree::Group(g), tail @ ..] if is_ident(vis, "pub") && g.delimiter() == Delimiter::Parenthesis => {
+                rest = tail;
+            }
+            [vis, tail @ ..] if is_ident(vis, "pub") => rest = tail,
+            _ => return rest,
+        }
+    }
+}
+
+// 按顶层的逗号拆分 Vec<T>这样的泛型参数中的尖括号不是Group 需要自己记录深度 以免把 HashMap<K, V> 拆开
+fn split_commas(tokens: Vec<TokenTree>) -> Vec<Vec<TokenTree>> {
+    let mut parts = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0usize;
+    let mut after_dash = false;
+    for tt in tokens {
+        if let TokenTree::Punct(p) = &tt {
+            match p.as_char() {
+                '<' => depth += 1,
+                // -> 中的 > 不是尖括号
+                '>' if !after_dash => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    parts.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            after_dash = p.as_char() == '-' && p.spacing() == Spacing::Joint;
+        } else {
+            after_dash = false;
+        }
+        current.push(tt);
+    }
+    parts.push(current);
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+// 把类型的token拼回源代码的写法 只在两个单词之间加空格 例如 & 'a mut Vec < u8 > => &'a mut Vec<u8>
+fn type_string(tokens: &[TokenTree]) -> String {
+    let mut out = String::new();
+    let mut prev_word = false;
+    for tt in tokens {
+        match tt {
+            TokenTree::Group(g) => {
+                let (open, close) = match g.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::None => ("", ""),
+                };
+                let inner: Vec<TokenTree> = g.stream().into_iter().collect();
+                out.push_str(open);
+                out.push_str(&type_string(&inner));
+                out.push_str(close);
+                prev_word = false;
+            }
+            TokenTree::Ident(_) | TokenTree::Literal(_) => {
+                if prev_word {
+                    out.push(' ');
+                }
+                out.push_str(&tt.to_string());
+                prev_word = true;
+            }
+            TokenTree::Punct(p) => {
+                out.push(p.as_char());
+                if matches!(p.as_char(), ',' | ';') {
+                    out.push(' ');
+                }
+                prev_word = false;
+            }
+        }
+    }
+    out
+}
+
+fn parse_fields(group: Option<&TokenTree>) -> Result<Fields, String> {
+    let Some(TokenTree::Group(g)) = group else {
+        return Ok(Fields::Unit);
+    };
+    let parts = split_commas(g.stream().into_iter().collect());
+    match g.delimiter() {
+        Delimiter::Brace => {
+            let mut fields = Vec::new();
+            for part in &parts {
+                match skip_attrs_and_vis(part) {
+                    [TokenTree::Ident(name), colon, ty @ ..] if is_punct(colon, ':') => {
+                        fields.push((name.to_string(), type_string(ty)));
+                    }
+                    _ => return Err(format!("无法解析字段 {}", type_string(part))),
+                }
+            }
+            Ok(Fields::Named(fields))
+        }
+        Delimiter::Parenthesis => Ok(Fields::Unnamed(parts.iter().map(|p| type_string(skip_attrs_and_vis(p))).collect())),
+        _ => Err(String::from("无法解析字段列表")),
+    }
+}
+
+fn parse_item(input: TokenStream) -> Result<Item, String> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let (keyword, name, rest) = match skip_attrs_and_vis(&tokens) {
+        [TokenTree::Ident(keyword), TokenTree::Ident(name), rest @ ..] => (keyword.to_string(), name.to_string(), rest),
+        _ => return Err(String::from("Describe 只能用于结构体和枚举")),
+    };
+    if rest.first().is_some_and(|tt| is_punct(tt, '<')) {
+        return Err(format!("Describe 暂不支持带泛型参数的类型 {}", name));
+    }
+    match keyword.as_str() {
+        "struct" => Ok(Item::Struct { name, fields: parse_fields(rest.first())? }),
+        "enum" => {
+            let Some(TokenTree::Group(body)) = rest.first() else {
+                return Err(format!("枚举 {} 缺少变体列表", name));
+            };
+            let mut variants = Vec::new();
+            for part in split_commas(body.stream().into_iter().collect()) {
+                // 变体后面可能有 = 判别值 这里不需要
+                match skip_attrs_and_vis(&part) {
+                    [TokenTree::Ident(variant), tail @ ..] => variants.push(Variant {
+                        name: variant.to_string(),
+                        fields: parse_fields(tail.first().filter(|tt| matches!(tt, TokenTree::Group(_))))?,
+                    }),
+                    _ => return Err(format!("无法解析枚举 {} 的变体", name)),
+                }
+            }
+            Ok(Item::Enum { name, variants })
+        }
+        "union" => Err(String::from("Describe 不支持union")),
+        _ => Err(String::from("Describe 只能用于结构体和枚举")),
+    }
+}
+
+// r#type 这样的原始标识符 显示时去掉 r#
+fn display_name(name: &str) -> &str {
+    name.strip_prefix("r#").unwrap_or(name)
+}
+
+// 生成 "name: Type = {:?}" 的format!调用 value是取值的表达式
+fn field_part(name: &str, ty: &str, value: &str) -> String {
+    format!("format!(\"{{}}: {{}} = {{:?}}\", {:?}, {:?}, {})", display_name(name), ty, value)
+}
+
+// 生成拼接各个字段描述的表达式 values是每个字段对应的取值表达式
+fn describe_fields(title: &str, fields: &Fields, values: &[String]) -> String {
+    let parts: Vec<String> = match fields {
+        Fields::Named(named) => named.iter().zip(values).map(|((name, ty), value)| field_part(name, ty, value)).collect(),
+        Fields::Unnamed(types) => {
+            types.iter().enumerate().zip(values).map(|((i, ty), value)| field_part(&i.to_string(), ty, value)).collect()
+        }
+        Fields::Unit => return format!("String::from({:?})", title),
+    };
+    let (open, close) = if matches!(fields, Fields::Named(_)) { (" { ", " }") } else { ("(", ")") };
+    format!(
+        "format!(\"{{}}{{}}{{}}{{}}\", {:?}, {:?}, [{}].join(\", \"), {:?})",
+        title,
+        open,
+        parts.join(", "),
+        close
+    )
+}
+
+fn field_names(fields: &Fields) -> Vec<String> {
+    match fields {
+        Fields::Named(named) => named.iter().map(|(name, _)| name.clone()).collect(),
+        Fields::Unnamed(types) => (0..types.len()).map(|i| i.to_string()).collect(),
+        Fields::Unit => Vec::new(),
+    }
+}
+
+fn expand(item: &Item) -> String {
+    let (name, body) = match item {
+        Item::Struct { name, fields } => {
+            let list: Vec<String> = match fields {
+                Fields::Named(named) => named.iter().map(|(n, ty)| format!("({:?}, {:?})", display_name(n), ty)).collect(),
+                Fields::Unnamed(types) => types.iter().enumerate().map(|(i, ty)| format!("(\"{}\", {:?})", i, ty)).collect(),
+                Fields::Unit => Vec::new(),
+            };
+            let values: Vec<String> = field_names(fields).iter().map(|f| format!("&self.{}", f)).collect();
+            let body = format!(
+                "    pub const FIELDS: &'static [(&'static str, &'static str)] = &[{}];\n\
+                 \n    pub fn describe(&self) -> String {{\n        {}\n    }}\n",
+                list.join(", "),
+                describe_fields(name, fields, &values)
+            );
+            (name, body)
+        }
+        Item::Enum { name, variants } => {
+            let names: Vec<String> = variants.iter().map(|v| format!("{:?}", v.name)).collect();
+            let mut name_arms = String::new();
+            let mut describe_arms = String::new();
+            for variant in variants {
+                // 匹配时把字段绑定到 __f0 __f1 ... 上 避免和字段名冲突
+                let bindings: Vec<String> = (0..field_names(&variant.fields).len()).map(|i| format!("__f{}", i)).collect();
+                let pattern = match &variant.fields {
+                    Fields::Named(named) => {
+                        let pairs: Vec<String> =
+                            named.iter().zip(&bindings).map(|((n, _), b)| format!("{}: {}", n, b)).collect();
+                        format!("Self::{} {{ {} }}", variant.name, pairs.join(", "))
+                    }
+                    Fields::Unnamed(_) => format!("Self::{}({})", variant.name, bindings.join(", ")),
+                    Fields::Unit => format!("Self::{}", variant.name),
+                };
+                let wildcard = match &variant.fields {
+                    Fields::Named(_) => format!("Self::{} {{ .. }}", variant.name),
+                    Fields::Unnamed(_) => format!("Self::{}(..)", variant.name),
+                    Fields::Unit => format!("Self::{}", variant.name),
+                };
+                let title = format!("{}::{}", name, variant.name);
+                name_arms.push_str(&format!("            {} => {:?},\n", wildcard, variant.name));
+                describe_arms.push_str(&format!(
+                    "            {} => {},\n",
+                    pattern,
+                    describe_fields(&title, &variant.fields, &bindings)
+                ));
+            }
+            // 没有变体的枚举无法构造 对 *self 做一个空的match即可 对引用做空match编译器会认为没有覆盖所有情况
+            let scrutinee = if variants.is_empty() { "*self" } else { "self" };
+            let body = format!(
+                "    pub const VARIANTS: &'static [&'static str] = &[{}];\n\
+                 \n    pub fn variant_name(&self) -> &'static str {{\n        match {} {{\n{}        }}\n    }}\n\
+                 \n    pub fn describe(&self) -> String {{\n        match {} {{\n{}        }}\n    }}\n",
+                names.join(", "),
+                scrutinee,
+                name_arms,
+                scrutinee,
+                describe_arms
+            );
+            (name, body)
+        }
+    };
+    // 派生出来的方法不一定都会被用到 不希望因此产生dead_code警告
+    let code = format!("#[allow(dead_code)]\nimpl {} {{\n{}}}\n", name, body);
+    // 把生成的代码本身也作为常量放进去 单独一个impl块 避免常量的内容包含它自己
+    format!("{}\n#[allow(dead_code)]\nimpl {} {{\n    pub const DERIVE_EXPANSION: &'static str = {:?};\n}}\n", code, name, code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expanded(src: &str) -> Result<String, String> {
+        parse_item(src.parse().expect("测试输入应该是合法的token")).map(|item| expand(&item))
+    }
+
+    // expand 在生成的impl后面追加一个保存展开结果的常量
+    fn with_expansion_const(name: &str, code: &str) -> String {
+        format!("{}\n#[allow(dead_code)]\nimpl {} {{\n    pub const DERIVE_EXPANSION: &'static str = {:?};\n}}\n", code, name, code)
+    }
+
+    #[test]
+    fn expands_named_struct() {
+        let code = r#"#[allow(dead_code)]
+impl Point {
+    pub const FIELDS: &'static [(&'static str, &'static str)] = &[("x", "i32"), ("y", "Vec<u8>")];
+
+    pub fn describe(&self) -> String {
+        format!("{}{}{}{}", "Point", " { ", [format!("{}: {} = {:?}", "x", "i32", &self.x), format!("{}: {} = {:?}", "y", "Vec<u8>", &self.y)].join(", "), " }")
+    }
+}
+"#;
+        let src = "#[derive(Debug)] pub struct Point { pub x: i32, #[allow(unused)] y: Vec<u8> }";
+        assert_eq!(expanded(src), Ok(with_expansion_const("Point", code)));
+    }
+
+    #[test]
+    fn expands_tuple_and_unit_structs() {
+        let code = r#"#[allow(dead_code)]
+impl T {
+    pub const FIELDS: &'static [(&'static str, &'static str)] = &[("0", "u8"), ("1", "&'static str")];
+
+    pub fn describe(&self) -> String {
+        format!("{}{}{}{}", "T", "(", [format!("{}: {} = {:?}", "0", "u8", &self.0), format!("{}: {} = {:?}", "1", "&'static str", &self.1)].join(", "), ")")
+    }
+}
+"#;
+        assert_eq!(expanded("struct T(pub(crate) u8, &'static str);"), Ok(with_expansion_const("T", code)));
+        let code = r#"#[allow(dead_code)]
+impl U {
+    pub const FIELDS: &'static [(&'static str, &'static str)] = &[];
+
+    pub fn describe(&self) -> String {
+        String::from("U")
+    }
+}
+"#;
+        assert_eq!(expanded("struct U;"), Ok(with_expansion_const("U", code)));
+    }
+
+    #[test]
+    fn expands_enum() {
+        let code = r#"#[allow(dead_code)]
+impl E {
+    pub const VARIANTS: &'static [&'static str] = &["A", "B", "C"];
+
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::B(..) => "B",
+            Self::C { .. } => "C",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::A => String::from("E::A"),
+            Self::B(__f0) => format!("{}{}{}{}", "E::B", "(", [format!("{}: {} = {:?}", "0", "u8", __f0)].join(", "), ")"),
+            Self::C { x: __f0 } => format!("{}{}{}{}", "E::C", " { ", [format!("{}: {} = {:?}", "x", "i32", __f0)].join(", "), " }"),
+        }
+    }
+}
+"#;
+        assert_eq!(expanded("enum E { A = 1, B(u8), C { x: i32 } }"), Ok(with_expansion_const("E", code)));
+    }
+
+    #[test]
+    fn empty_enum_matches_on_deref() {
+        let code = expanded("enum Never {}").unwrap();
+        assert!(code.contains("pub const VARIANTS: &'static [&'static str] = &[];"));
+        assert!(code.contains("match *self {\n        }"));
+    }
+
+    #[test]
+    fn keeps_nested_types_together() {
+        let code = expanded("struct A { m: HashMap<String, Vec<u8>>, f: fn(u8) -> u8, r#type: [u8; 4] }").unwrap();
+        let fields = r#"&[("m", "HashMap<String, Vec<u8>>"), ("f", "fn(u8)->u8"), ("type", "[u8; 4]")]"#;
+        assert!(code.contains(fields), "{}", code);
+        // 原始标识符取值时仍然要写 r#type
+        assert!(code.contains("&self.r#type"));
+    }
+
+    #[test]
+    fn rejects_unsupported_items() {
+        let cases = [
+            ("union U { a: u8, b: f32 }", "Describe 不支持union"),
+            ("struct W<T> { t: T }", "Describe 暂不支持带泛型参数的类型 W"),
+            ("enum O<'a> { R(&'a str) }", "Describe 暂不支持带泛型参数的类型 O"),
+            ("fn f() {}", "Describe 只能用于结构体和枚举"),
+            ("impl A {}", "Describe 只能用于结构体和枚举"),
+            ("enum E;", "枚举 E 缺少变体列表"),
+            ("struct A { x }", "无法解析字段 x"),
+            ("struct A { x: u8, 1: u8 }", "无法解析字段 1:u8"),
+            ("enum E { 1 }", "无法解析枚举 E 的变体"),
+        ];
+        for (src, message) in cases {
+            assert_eq!(expanded(src), Err(String::from(message)), "{}", src);
+        }
+    }
+
+    #[test]
+    fn errors_become_compile_error() {
+        let tokens = describe("union U { a: u8 }".parse().unwrap());
+        assert_eq!(tokens.to_string(), compile_error("Describe 不支持union").to_string());
+        assert_eq!(tokens.to_string(), r#"compile_error ! ("Describe 不支持union") ;"#);
+        // 正常的输入展开后没有compile_error
+        let tokens = describe("struct P { x: i32 }".parse().unwrap());
+        assert!(!tokens.to_string().contains("compile_error"));
+    }
+}
@@ -0,0 +1,54 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// 原子地覆盖一个文件 先完整地写到同目录下的临时文件(原文件名加.tmp)并刷到磁盘 再rename覆盖原文件
+// 同一个文件系统内的rename是原子的 读者要么看到旧文件 要么看到新文件 写到一半程序崩溃也不会损坏原文件
+pub fn write<P: AsRef<Path>>(path: P, contents: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut tmp = path.to_path_buf().into_os_string();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    // rename失败时原文件保持不变 把临时文件删掉 不留下垃圾
+    if let Err(err) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_file_and_removes_temp() {
+        let path = std::env::temp_dir().join(format!("myrust_atomic_{}.txt", std::process::id()));
+        fs::write(&path, "old").unwrap();
+        write(&path, "new".as_bytes()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!path.with_extension("txt.tmp").exists());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn failed_rename_removes_temp() {
+        // 目标是一个非空目录 文件不能rename覆盖它
+        let path = std::env::temp_dir().join(format!("myrust_atomic_dir_{}", std::process::id()));
+        fs::create_dir_all(path.join("child")).unwrap();
+        assert!(write(&path, b"x").is_err());
+        assert!(!path.with_extension("tmp").exists());
+        let _ = fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn missing_directory_is_an_error() {
+        let path = std::env::temp_dir().join(format!("myrust_atomic_missing_{}", std::process::id())).join("a.txt");
+        assert_eq!(write(&path, b"x").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
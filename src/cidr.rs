@@ -0,0 +1,405 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ip::IpParseError;
+use crate::unicode::{pad, Align};
+use crate::{IpAddr, IpAddrKind};
+
+// CIDR表示法 地址/前缀长度 例如 10.0.0.0/8 表示前8位固定 剩下的24位可以任意取值的一段地址
+// 地址统一转换成u128做位运算(见 IpAddr::to_bits) IPv4只使用低32位
+// 解析时允许地址中带有主机位 例如 192.168.1.77/24 会被规范成网络地址 192.168.1.0/24
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CidrError {
+    Address(IpParseError),
+    // 缺少 /前缀长度
+    MissingPrefix,
+    InvalidPrefix(String),
+    // 前缀长度超过了地址的位数
+    PrefixTooLong { prefix: u8, max: u8 },
+    // 拆分子网时 新的前缀必须在当前前缀和最大位数之间
+    InvalidSubnetPrefix { prefix: u8, new_prefix: u8 },
+}
+
+impl fmt::Display for CidrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CidrError::Address(err) => write!(f, "地址不合法: {}", err),
+            CidrError::MissingPrefix => write!(f, "缺少前缀长度 例如 10.0.0.0/8"),
+            CidrError::InvalidPrefix(prefix) => write!(f, "{:?} 不是合法的前缀长度", prefix),
+            CidrError::PrefixTooLong { prefix, max } => write!(f, "前缀长度{}超过了{}", prefix, max),
+            CidrError::InvalidSubnetPrefix { prefix, new_prefix } => {
+                write!(f, "不能把/{}拆分成/{}的子网", prefix, new_prefix)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CidrError {}
+
+impl From<IpParseError> for CidrError {
+    fn from(err: IpParseError) -> CidrError {
+        CidrError::Address(err)
+    }
+}
+
+// 前prefix位为1 其余为0的掩码 只保留width位
+fn mask(width: u8, prefix: u8) -> u128 {
+    let all = u128::MAX >> (128 - width as u32);
+    if prefix == 0 {
+        0
+    } else {
+        (u128::MAX << (width - prefix) as u32) & all
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    // 地址中的主机位会被清零
+    pub fn new(address: IpAddr, prefix: u8) -> Result<Cidr, CidrError> {
+        let kind = address.kind();
+        let max = kind.width();
+        if prefix > max {
+            return Err(CidrError::PrefixTooLong { prefix, max });
+        }
+        Ok(Cidr { network: IpAddr::from_bits(kind, address.to_bits() & mask(max, prefix)), prefix })
+    }
+
+    pub fn kind(&self) -> IpAddrKind {
+        self.network.kind()
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    fn width(&self) -> u8 {
+        self.kind().width()
+    }
+
+    fn first_bits(&self) -> u128 {
+        self.network.to_bits()
+    }
+
+    fn last_bits(&self) -> u128 {
+        self.first_bits() | (!mask(self.width(), self.prefix) & mask(self.width(), self.width()))
+    }
+
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    // 网段中的最后一个地址 对IPv4来说就是广播地址
+    pub fn last(&self) -> IpAddr {
+        IpAddr::from_bits(self.kind(), self.last_bits())
+    }
+
+    // IPv6没有广播地址
+    pub fn broadcast(&self) -> Option<IpAddr> {
+        match self.kind() {
+            IpAddrKind::V4 => Some(self.last()),
+            IpAddrKind::V6 => None,
+        }
+    }
+
+    pub fn netmask(&self) -> IpAddr {
+        IpAddr::from_bits(self.kind(), mask(self.width(), self.prefix))
+    }
+
+    // 网段中的地址总数 ::/0 有2^128个地址 超出了u128的范围 返回u128::MAX
+    pub fn size(&self) -> u128 {
+        1u128.checked_shl((self.width() - self.prefix) as u32).unwrap_or(u128::MAX)
+    }
+
+    // 可以分配给主机的地址范围
+    // IPv4去掉网络地址和广播地址 但/31(点对点链路 RFC 3021)和/32例外 IPv6的所有地址都可以使用
+    fn host_range(&self) -> (u128, u128) {
+        if self.kind() == IpAddrKind::V4 && self.prefix < 31 {
+            (self.first_bits() + 1, self.last_bits() - 1)
+        } else {
+            (self.first_bits(), self.last_bits())
+        }
+    }
+
+    pub fn host_count(&self) -> u128 {
+        let (first, last) = self.host_range();
+        (last - first).saturating_add(1)
+    }
+
+    pub fn hosts(&self) -> Hosts {
+        let (first, last) = self.host_range();
+        Hosts { kind: self.kind(), next: Some(first), last }
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        ip.kind() == self.kind() && (ip.to_bits() & mask(self.width(), self.prefix)) == self.first_bits()
+    }
+
+    pub fn contains_cidr(&self, other: &Cidr) -> bool {
+        other.prefix >= self.prefix && self.contains(&other.network)
+    }
+
+    // 拆分成前缀为new_prefix的子网 例如 /24 拆分成 /26 得到4个子网
+    pub fn subnets(&self, new_prefix: u8) -> Result<Subnets, CidrError> {
+        if new_prefix < self.prefix || new_prefix > self.width() {
+            return Err(CidrError::InvalidSubnetPrefix { prefix: self.prefix, new_prefix });
+        }
+        let step = 1u128.checked_shl((self.width() - new_prefix) as u32).unwrap_or(0);
+        Ok(Subnets { kind: self.kind(), prefix: new_prefix, next: Some(self.first_bits()), last: self.last_bits(), step })
+    }
+
+    // 前缀短一位的上级网段
+    pub fn supernet(&self) -> Option<Cidr> {
+        if self.prefix == 0 {
+            None
+        } else {
+            Cidr::new(self.network, self.prefix - 1).ok()
+        }
+    }
+}
+
+// 把一组网段合并成最少的网段 被包含的网段会被去掉 相邻的两个兄弟网段合并成它们的上级网段
+// 例如 10.0.0.0/25 和 10.0.0.128/25 合并成 10.0.0.0/24
+pub fn aggregate(cidrs: &[Cidr]) -> Vec<Cidr> {
+    let mut sorted = cidrs.to_vec();
+    // IPv4排在IPv6前面 同一个网络地址时前缀短(范围大)的在前
+    sorted.sort_by_key(|c| (c.kind() == IpAddrKind::V6, c.first_bits(), c.prefix));
+    let mut out: Vec<Cidr> = Vec::new();
+    for cidr in sorted {
+        if out.last().is_some_and(|last| last.contains_cidr(&cidr)) {
+            continue;
+        }
+        out.push(cidr);
+        // 栈顶的两个网段是兄弟时合并 合并后可能又和前一个网段成为兄弟
+        while out.len() >= 2 {
+            let (a, b) = (out[out.len() - 2], out[out.len() - 1]);
+            if a.kind() != b.kind() || a.prefix != b.prefix || a.supernet() != b.supernet() {
+                break;
+            }
+            out.truncate(out.len() - 2);
+            out.extend(a.supernet());
+        }
+    }
+    out
+}
+
+impl FromStr for Cidr {
+    type Err = CidrError;
+
+    fn from_str(s: &str) -> Result<Cidr, CidrError> {
+        let (address, prefix) = s.split_once('/').ok_or(CidrError::MissingPrefix)?;
+        let address: IpAddr = address.parse()?;
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_digit()) || prefix.len() > 3 {
+            return Err(CidrError::InvalidPrefix(prefix.to_string()));
+        }
+        let prefix: u16 = prefix.parse().map_err(|_| CidrError::InvalidPrefix(prefix.to_string()))?;
+        let max = address.kind().width();
+        if prefix > max as u16 {
+            return Err(CidrError::PrefixTooLong { prefix: prefix.min(255) as u8, max });
+        }
+        Cidr::new(address, prefix as u8)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("{}/{}", self.network, self.prefix))
+    }
+}
+
+// 按顺序遍历网段中可用的主机地址
+// IpAddr 是 main.rs 中的私有类型 作为Iterator::Item时迭代器本身不能是pub 所以用pub(crate)
+pub(crate) struct Hosts {
+    kind: IpAddrKind,
+    // None表示已经遍历完
+    next: Option<u128>,
+    last: u128,
+}
+
+impl Iterator for Hosts {
+    type Item = IpAddr;
+
+    fn next(&mut self) -> Option<IpAddr> {
+        let current = self.next.filter(|&n| n <= self.last)?;
+        // 到达最后一个地址时 +1可能溢出 所以用checked_add
+        self.next = if current == self.last { None } else { current.checked_add(1) };
+        Some(IpAddr::from_bits(self.kind, current))
+    }
+}
+
+pub(crate) struct Subnets {
+    kind: IpAddrKind,
+    prefix: u8,
+    next: Option<u128>,
+    last: u128,
+    step: u128,
+}
+
+impl Iterator for Subnets {
+    type Item = Cidr;
+
+    fn next(&mut self) -> Option<Cidr> {
+        let current = self.next.filter(|&n| n <= self.last)?;
+        self.next = current.checked_add(self.step).filter(|&n| self.step != 0 && n > current);
+        Some(Cidr { network: IpAddr::from_bits(self.kind, current), prefix: self.prefix })
+    }
+}
+
+// myrust cidr 192.168.1.0/24 打印网段的详细信息 解析失败时返回false
+pub fn print_breakdown(text: &str) -> bool {
+    let cidr: Cidr = match text.parse() {
+        Ok(cidr) => cidr,
+        Err(err) => {
+            eprintln!("{}: {}", text, err);
+            return false;
+        }
+    };
+    let row = |label: &str, value: String| println!("{}{}", pad(label, 12, Align::Left), value);
+    row("CIDR", cidr.to_string());
+    row("网络地址", cidr.network().to_string());
+    match cidr.broadcast() {
+        Some(broadcast) => row("广播地址", broadcast.to_string()),
+        None => row("最后地址", cidr.last().to_string()),
+    }
+    row("子网掩码", cidr.netmask().to_string());
+    let mut hosts = cidr.hosts();
+    if let Some(first) = hosts.next() {
+        let (_, last) = cidr.host_range();
+        row("可用主机", format!("{} - {}", first, IpAddr::from_bits(cidr.kind(), last)));
+    }
+    row("主机数量", cidr.host_count().to_string());
+    row("地址总数", cidr.size().to_string());
+    if let Some(supernet) = cidr.supernet() {
+        row("上级网段", supernet.to_string());
+    }
+    if let Ok(halves) = cidr.subnets(cidr.prefix() + 1) {
+        let halves: Vec<String> = halves.map(|c| c.to_string()).collect();
+        if !halves.is_empty() {
+            row("拆分为两半", halves.join(", "));
+        }
+    }
+    true
+}
+
+pub fn demo() {
+    print_breakdown("192.168.1.77/24");
+    print_breakdown("fe80::1/10");
+    if let Ok(net) = "192.168.1.0/24".parse::<Cidr>() {
+        for ip in ["192.168.1.200", "192.168.2.1", "::ffff:192.168.1.1"] {
+            if let Ok(ip) = ip.parse() {
+                println!("{} 包含 {}: {}", net, ip, net.contains(&ip));
+            }
+        }
+        if let Some(supernet) = net.supernet() {
+            println!("{} 包含 {}: {}", supernet, net, supernet.contains_cidr(&net));
+        }
+    }
+
+    // 相邻的网段合并成更大的网段 被包含的网段被去掉
+    let cidrs: Vec<Cidr> = ["10.0.0.128/26", "10.0.0.0/25", "10.0.0.192/26", "10.0.0.5/32", "10.0.1.0/24"]
+        .iter()
+        .filter_map(|s| s.parse().ok())
+        .collect();
+    let merged: Vec<String> = aggregate(&cidrs).iter().map(|c| c.to_string()).collect();
+    println!("合并后: {}", merged.join(", "));
+
+    for text in ["10.0.0.0", "fe80::/129"] {
+        if let Err(err) = text.parse::<Cidr>() {
+            println!("{}: {}", text, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn ipv4_network() {
+        let net = parse("192.168.1.77/24");
+        assert_eq!(net.to_string(), "192.168.1.0/24");
+        assert_eq!(net.broadcast().map(|b| b.to_string()), Some(String::from("192.168.1.255")));
+        assert_eq!(net.netmask().to_string(), "255.255.255.0");
+        assert_eq!((net.size(), net.host_count()), (256, 254));
+        let hosts: Vec<String> = net.hosts().map(|h| h.to_string()).collect();
+        assert_eq!((hosts[0].as_str(), hosts[253].as_str(), hosts.len()), ("192.168.1.1", "192.168.1.254", 254));
+        assert!(net.contains(&"192.168.1.200".parse().unwrap()));
+        assert!(!net.contains(&"192.168.2.1".parse().unwrap()));
+        // 类型不同的地址永远不在网段中
+        assert!(!net.contains(&"::ffff:192.168.1.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn subnets_and_supernet() {
+        let net = parse("192.168.1.0/24");
+        let quarters: Vec<String> = net.subnets(26).unwrap().map(|c| c.to_string()).collect();
+        assert_eq!(quarters, ["192.168.1.0/26", "192.168.1.64/26", "192.168.1.128/26", "192.168.1.192/26"]);
+        assert_eq!(net.subnets(23).err(), Some(CidrError::InvalidSubnetPrefix { prefix: 24, new_prefix: 23 }));
+        assert_eq!(net.supernet().map(|c| c.to_string()), Some(String::from("192.168.0.0/23")));
+        assert!(net.supernet().is_some_and(|s| s.contains_cidr(&net)));
+    }
+
+    // 点对点链路(/31)、单个主机(/32)和整个地址空间(/0)
+    #[test]
+    fn ipv4_edge_prefixes() {
+        assert_eq!(parse("10.0.0.0/31").hosts().count(), 2);
+        assert_eq!(parse("10.0.0.5/32").hosts().map(|h| h.to_string()).collect::<Vec<_>>(), ["10.0.0.5"]);
+        let all = parse("0.0.0.0/0");
+        assert_eq!((all.size(), all.netmask().to_string()), (1 << 32, String::from("0.0.0.0")));
+    }
+
+    #[test]
+    fn ipv6_network() {
+        let link_local = parse("fe80::1/10");
+        assert_eq!(link_local.to_string(), "fe80::/10");
+        assert_eq!(link_local.last().to_string(), "febf:ffff:ffff:ffff:ffff:ffff:ffff:ffff");
+        assert_eq!(link_local.broadcast(), None);
+        assert!(link_local.contains(&"fe80::dead:beef".parse().unwrap()));
+    }
+
+    // /0 的地址总数超过u128 用u128::MAX表示 /127 和 /31 一样两个地址都可用
+    #[test]
+    fn ipv6_edge_prefixes() {
+        assert_eq!(parse("::/0").size(), u128::MAX);
+        let last_two: Vec<String> = parse("ffff:ffff:ffff:ffff:ffff:ffff:ffff:fffe/127").hosts().map(|h| h.to_string()).collect();
+        assert_eq!(last_two, ["ffff:ffff:ffff:ffff:ffff:ffff:ffff:fffe", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"]);
+        assert_eq!(parse("::/0").subnets(1).unwrap().count(), 2);
+        assert_eq!(parse("::/127").subnets(128).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn aggregates_adjacent_and_contained() {
+        let merged: Vec<String> = aggregate(&[
+            parse("10.0.0.128/26"),
+            parse("10.0.0.0/25"),
+            parse("10.0.0.192/26"),
+            parse("10.0.0.5/32"),
+            parse("10.0.1.0/24"),
+            parse("2001:db8::/33"),
+            parse("2001:db8:8000::/33"),
+            parse("172.16.0.0/12"),
+        ])
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+        assert_eq!(merged, ["10.0.0.0/23", "172.16.0.0/12", "2001:db8::/32"]);
+    }
+
+    #[test]
+    fn rejects_invalid_cidrs() {
+        assert_eq!("10.0.0.0".parse::<Cidr>(), Err(CidrError::MissingPrefix));
+        assert_eq!("10.0.0.0/33".parse::<Cidr>(), Err(CidrError::PrefixTooLong { prefix: 33, max: 32 }));
+        assert_eq!("fe80::/129".parse::<Cidr>(), Err(CidrError::PrefixTooLong { prefix: 129, max: 128 }));
+        assert_eq!("10.0.0.0/x".parse::<Cidr>(), Err(CidrError::InvalidPrefix(String::from("x"))));
+        assert_eq!("10.0.0/8".parse::<Cidr>(), Err(CidrError::Address(IpParseError::Ipv4PartCount(3))));
+    }
+}
@@ -0,0 +1,363 @@
+use std::fmt;
+
+use crate::ip::{Ipv4, Ipv6};
+use crate::{IpAddr, IpInfo, Message};
+
+// IpInfo 和 Message 的二进制编码 用于在网络上传输或写入文件
+// 枚举的每个成员先写一个tag字节 再按顺序写成员携带的数据:
+//   整数: 固定宽度 小端序(little-endian)
+//   字符串: u32长度(字节数) + UTF-8字节
+//   地址: tag 4 + 4字节 或 tag 6 + 16字节 按网络字节序(大端) 和地址的文本写法顺序一致
+// 解码时不信任输入 长度不够、未知的tag、非法的UTF-8都返回DecodeError 而不是panic
+// 这就是用枚举的好处: 每种消息都有自己的tag 接收方一个match就能还原出具体的成员
+
+const TAG_V4: u8 = 4;
+const TAG_V6: u8 = 6;
+
+const TAG_QUIT: u8 = 0;
+const TAG_MOVE: u8 = 1;
+const TAG_WRITE: u8 = 2;
+const TAG_CHANGE_COLOR: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    // 在offset处需要needed个字节 但只剩下remaining个
+    UnexpectedEnd { offset: usize, needed: usize, remaining: usize },
+    // what是正在解码的类型
+    UnknownTag { what: &'static str, tag: u8, offset: usize },
+    InvalidUtf8 { offset: usize },
+    // 一个值解码完之后还有多余的字节
+    TrailingBytes { offset: usize, count: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd { offset, needed, remaining } => {
+                write!(f, "第{}字节处需要{}个字节 但只剩下{}个", offset, needed, remaining)
+            }
+            DecodeError::UnknownTag { what, tag, offset } => write!(f, "第{}字节处 {} 的tag {} 未知", offset, what, tag),
+            DecodeError::InvalidUtf8 { offset } => write!(f, "第{}字节处的字符串不是合法的UTF-8", offset),
+            DecodeError::TrailingBytes { offset, count } => write!(f, "第{}字节之后还有{}个多余的字节", offset, count),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+}
+
+pub trait Decode: Sized {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError>;
+
+    // 整个bytes必须恰好是一个值
+    fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let value = Self::decode(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(DecodeError::TrailingBytes { offset: reader.offset(), count: reader.remaining() });
+        }
+        Ok(value)
+    }
+}
+
+// 从字节切片中依次读取 记录当前位置用于错误信息
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, offset: 0 }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if n > self.remaining() {
+            return Err(DecodeError::UnexpectedEnd { offset: self.offset, needed: n, remaining: self.remaining() });
+        }
+        let bytes = &self.bytes[self.offset..self.offset + n];
+        self.offset += n;
+        Ok(bytes)
+    }
+
+    // 长度已经检查过 try_into 不会失败
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, DecodeError> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    // 先检查长度再读取 所以恶意的超大长度只会得到UnexpectedEnd 不会预先分配内存
+    pub fn string(&mut self) -> Result<String, DecodeError> {
+        let len = self.u32()? as usize;
+        let offset = self.offset;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8 { offset })
+    }
+}
+
+fn put_i32(out: &mut Vec<u8>, n: i32) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn put_string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+impl Encode for IpAddr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            IpAddr::V4(ip) => {
+                out.push(TAG_V4);
+                out.extend_from_slice(&ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                out.push(TAG_V6);
+                for segment in ip.segments() {
+                    out.extend_from_slice(&segment.to_be_bytes());
+                }
+            }
+        }
+    }
+}
+
+impl Decode for IpAddr {
+    fn decode(reader: &mut Reader<'_>) -> Result<IpAddr, DecodeError> {
+        let offset = reader.offset();
+        match reader.u8()? {
+            TAG_V4 => {
+                let [a, b, c, d] = reader.array()?;
+                Ok(IpAddr::V4(Ipv4::new(a, b, c, d)))
+            }
+            TAG_V6 => {
+                let bytes: [u8; 16] = reader.array()?;
+                let mut segments = [0u16; 8];
+                for (i, segment) in segments.iter_mut().enumerate() {
+                    *segment = u16::from_be_bytes([bytes[2 * i], bytes[2 * i + 1]]);
+                }
+                Ok(IpAddr::V6(Ipv6::new(segments)))
+            }
+            tag => Err(DecodeError::UnknownTag { what: "IpAddr", tag, offset }),
+        }
+    }
+}
+
+// IpInfo的kind由地址决定 所以只需要编码地址
+impl Encode for IpInfo {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.address.encode(out);
+    }
+}
+
+impl Decode for IpInfo {
+    fn decode(reader: &mut Reader<'_>) -> Result<IpInfo, DecodeError> {
+        Ok(IpInfo::new(IpAddr::decode(reader)?))
+    }
+}
+
+impl Encode for Message {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Message::Quit => out.push(TAG_QUIT),
+            Message::Move { x, y } => {
+                out.push(TAG_MOVE);
+                put_i32(out, *x);
+                put_i32(out, *y);
+            }
+            Message::Write(text) => {
+                out.push(TAG_WRITE);
+                put_string(out, text);
+            }
+            Message::ChangeColor(r, g, b) => {
+                out.push(TAG_CHANGE_COLOR);
+                put_i32(out, *r);
+                put_i32(out, *g);
+                put_i32(out, *b);
+            }
+        }
+    }
+}
+
+impl Decode for Message {
+    fn decode(reader: &mut Reader<'_>) -> Result<Message, DecodeError> {
+        let offset = reader.offset();
+        match reader.u8()? {
+            TAG_QUIT => Ok(Message::Quit),
+            TAG_MOVE => Ok(Message::Move { x: reader.i32()?, y: reader.i32()? }),
+            TAG_WRITE => Ok(Message::Write(reader.string()?)),
+            TAG_CHANGE_COLOR => Ok(Message::ChangeColor(reader.i32()?, reader.i32()?, reader.i32()?)),
+            tag => Err(DecodeError::UnknownTag { what: "Message", tag, offset }),
+        }
+    }
+}
+
+// 多条消息首尾相接 依次解码直到读完
+pub fn decode_all<T: Decode>(bytes: &[u8]) -> Result<Vec<T>, DecodeError> {
+    let mut reader = Reader::new(bytes);
+    let mut values = Vec::new();
+    while !reader.is_empty() {
+        values.push(T::decode(&mut reader)?);
+    }
+    Ok(values)
+}
+
+pub fn demo() {
+    let messages =
+        [Message::Quit, Message::Move { x: 1, y: -2 }, Message::Write(String::from("hi你")), Message::ChangeColor(255, 0, 0)];
+    let mut stream = Vec::new();
+    for msg in &messages {
+        // 整数用小端序 字符串前面是4字节的长度
+        println!("{:?} => {:02X?}", msg, msg.to_bytes());
+        msg.encode(&mut stream);
+    }
+    if let Ok(decoded) = decode_all::<Message>(&stream) {
+        println!("{}条消息编码后共{}字节 解码回{}条", messages.len(), stream.len(), decoded.len());
+    }
+    let info = IpInfo::new(IpAddr::V4(Ipv4::LOCALHOST));
+    println!("{:?} => {:?}", info.address, info.to_bytes());
+
+    // 结构化的错误 会指出出错的位置
+    let bad: [&[u8]; 5] = [&[9], &[TAG_MOVE, 1, 0], &[TAG_WRITE, 2, 0, 0, 0, 0xC3, 0x28], &[TAG_QUIT, TAG_QUIT], &[]];
+    for bytes in bad {
+        if let Err(err) = Message::from_bytes(bytes) {
+            println!("{:?} => {}", bytes, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+
+    fn random_i32(rng: &mut XorShift) -> i32 {
+        // 一半概率取边界值 边界最容易出错
+        match rng.below(4) {
+            0 => [i32::MIN, -1, 0, 1, i32::MAX][rng.below(5)],
+            _ => rng.next_u64() as i32,
+        }
+    }
+
+    fn random_message(rng: &mut XorShift) -> Message {
+        match rng.below(4) {
+            0 => Message::Quit,
+            1 => Message::Move { x: random_i32(rng), y: random_i32(rng) },
+            2 => Message::Write(rng.next_string(20)),
+            _ => Message::ChangeColor(random_i32(rng), random_i32(rng), random_i32(rng)),
+        }
+    }
+
+    fn random_ip(rng: &mut XorShift) -> IpAddr {
+        let bits = (rng.next_u64() as u128) << 64 | rng.next_u64() as u128;
+        if rng.below(2) == 0 {
+            IpAddr::V4(Ipv4::from_bits(bits as u32))
+        } else {
+            IpAddr::V6(Ipv6::from_bits(bits))
+        }
+    }
+
+    #[test]
+    fn encodes_known_layout() {
+        let bytes = Message::Move { x: 1, y: -2 }.to_bytes();
+        assert_eq!(bytes, [TAG_MOVE, 1, 0, 0, 0, 0xFE, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(Message::Quit.to_bytes(), [TAG_QUIT]);
+        assert_eq!(Message::Write(String::from("hi你")).to_bytes(), [TAG_WRITE, 5, 0, 0, 0, b'h', b'i', 0xE4, 0xBD, 0xA0]);
+        let info = IpInfo::new(IpAddr::V4(Ipv4::LOCALHOST));
+        assert_eq!(info.to_bytes(), [TAG_V4, 127, 0, 0, 1]);
+        let info = IpInfo::new(IpAddr::V6(Ipv6::LOCALHOST));
+        let bytes = info.to_bytes();
+        assert_eq!((bytes.len(), bytes[0], bytes[16]), (17, TAG_V6, 1));
+        assert_eq!(IpInfo::from_bytes(&bytes).unwrap().kind(), info.kind());
+    }
+
+    #[test]
+    fn reports_structured_errors() {
+        let errors = [
+            (vec![9], DecodeError::UnknownTag { what: "Message", tag: 9, offset: 0 }),
+            (vec![TAG_MOVE, 1, 0], DecodeError::UnexpectedEnd { offset: 1, needed: 4, remaining: 2 }),
+            (vec![TAG_WRITE, 0xFF, 0xFF, 0xFF, 0xFF], DecodeError::UnexpectedEnd { offset: 5, needed: 0xFFFF_FFFF, remaining: 0 }),
+            (vec![TAG_WRITE, 2, 0, 0, 0, 0xC3, 0x28], DecodeError::InvalidUtf8 { offset: 5 }),
+            (vec![TAG_QUIT, TAG_QUIT], DecodeError::TrailingBytes { offset: 1, count: 1 }),
+            (vec![], DecodeError::UnexpectedEnd { offset: 0, needed: 1, remaining: 0 }),
+        ];
+        for (bytes, expected) in errors {
+            assert_eq!(Message::from_bytes(&bytes), Err(expected));
+        }
+        assert_eq!(
+            IpInfo::from_bytes(&[5, 1, 2, 3, 4]).err(),
+            Some(DecodeError::UnknownTag { what: "IpAddr", tag: 5, offset: 0 })
+        );
+    }
+
+    // 编码后再解码必须得到原来的值 任何截断都必须报告UnexpectedEnd 而不是panic或解码出别的消息
+    #[test]
+    fn round_trips_random_values() {
+        let mut rng = XorShift::new(45);
+        let mut stream = Vec::new();
+        let mut messages = Vec::new();
+        for _ in 0..500 {
+            let msg = random_message(&mut rng);
+            let bytes = msg.to_bytes();
+            assert_eq!(Message::from_bytes(&bytes).as_ref(), Ok(&msg));
+            for len in 0..bytes.len() {
+                assert!(matches!(Message::from_bytes(&bytes[..len]), Err(DecodeError::UnexpectedEnd { .. })));
+            }
+            msg.encode(&mut stream);
+            messages.push(msg);
+
+            let info = IpInfo::new(random_ip(&mut rng));
+            let decoded = IpInfo::from_bytes(&info.to_bytes()).unwrap();
+            assert_eq!((decoded.address, decoded.kind()), (info.address, info.kind()));
+        }
+        assert_eq!(decode_all::<Message>(&stream).unwrap(), messages);
+    }
+
+    // 随机字节 解码只能成功或返回错误 不能panic 成功时重新编码必须得到相同的字节
+    #[test]
+    fn random_bytes_never_panic() {
+        let mut rng = XorShift::new(45);
+        for _ in 0..2000 {
+            let len = rng.below(16);
+            let bytes: Vec<u8> =
+                (0..len).map(|_| if rng.below(3) == 0 { rng.below(4) as u8 } else { rng.next_u64() as u8 }).collect();
+            if let Ok(msg) = Message::from_bytes(&bytes) {
+                assert_eq!(msg.to_bytes(), bytes);
+            }
+            if let Ok(info) = IpInfo::from_bytes(&bytes) {
+                assert_eq!(info.to_bytes(), bytes);
+            }
+        }
+    }
+}
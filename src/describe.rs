@@ -0,0 +1,44 @@
+use crate::{IpInfo, Message, User};
+
+// #[derive(Describe)] 生成的代码 派生宏的实现在 describe_derive/src/lib.rs
+// 派生宏在编译期运行 读取类型定义的token 生成一个新的impl块追加到代码中
+// DERIVE_EXPANSION 是宏生成的代码本身 相当于 cargo expand 的输出
+
+pub fn demo() {
+    println!("{}", User::DERIVE_EXPANSION);
+    assert!(User::DERIVE_EXPANSION.starts_with("#[allow(dead_code)]\nimpl User {"));
+    assert!(Message::DERIVE_EXPANSION.contains("Self::Move { x: __f0, y: __f1 } =>"));
+
+    // 结构体 字段名、类型和值
+    assert_eq!(
+        User::FIELDS,
+        [("username", "String"), ("email", "String"), ("sign_in_count", "u64"), ("active", "bool")]
+    );
+    let user = User::builder().username("张三").email("zhangsan@example.com").build().unwrap();
+    assert_eq!(
+        user.describe(),
+        r#"User { username: String = "张三", email: String = "zhangsan@example.com", sign_in_count: u64 = 1, active: bool = true }"#
+    );
+    let ip: IpInfo = "127.0.0.1".parse().unwrap();
+    assert_eq!(IpInfo::FIELDS, [("address", "IpAddr")]);
+    assert_eq!(ip.describe(), "IpInfo { address: IpAddr = V4(127.0.0.1) }");
+    println!("{}", ip.describe());
+
+    // 枚举 变体名以及变体中的字段
+    assert_eq!(Message::VARIANTS, ["Quit", "Move", "Write", "ChangeColor"]);
+    let messages = [
+        (Message::Quit, "Quit", "Message::Quit"),
+        (Message::Move { x: 1, y: 2 }, "Move", "Message::Move { x: i32 = 1, y: i32 = 2 }"),
+        (Message::Write(String::from("hi")), "Write", r#"Message::Write(0: String = "hi")"#),
+        (
+            Message::ChangeColor(255, 0, 0),
+            "ChangeColor",
+            "Message::ChangeColor(0: i32 = 255, 1: i32 = 0, 2: i32 = 0)",
+        ),
+    ];
+    for (message, variant, description) in messages {
+        assert_eq!(message.variant_name(), variant);
+        assert_eq!(message.describe(), description);
+        println!("{}", message.describe());
+    }
+}
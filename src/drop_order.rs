@@ -0,0 +1,216 @@
+use std::cell::RefCell;
+
+// drop顺序 用一个在drop时往日志里写名字的类型 验证Rust释放值的顺序
+// 规则总结:
+// 1.局部变量在作用域结束时按声明的相反顺序drop 被遮蔽的变量不会提前drop
+// 2.结构体先调用自身的Drop 再按字段的声明顺序drop字段 元组和数组/Vec的元素按下标顺序drop
+// 3.临时值在所在语句结束时drop 但 let r = &临时值; 会把临时值的生命周期延长到r的作用域结束
+// 4.给变量重新赋值时 旧值立刻被drop let _ = 表达式; 不会绑定 值立刻被drop
+
+thread_local! {
+    static LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn log(name: &str) {
+    LOG.with(|l| l.borrow_mut().push(name.to_string()));
+}
+
+// 取出并清空到目前为止的drop记录
+pub fn take_log() -> Vec<String> {
+    LOG.with(|l| std::mem::take(&mut *l.borrow_mut()))
+}
+
+pub struct Noisy {
+    name: String,
+}
+
+impl Noisy {
+    pub fn new(name: &str) -> Noisy {
+        Noisy { name: name.to_string() }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for Noisy {
+    fn drop(&mut self) {
+        log(&self.name);
+    }
+}
+
+// 自身也实现了Drop的结构体 用来观察"先外后内"的顺序
+struct Outer {
+    first: Noisy,
+    second: Noisy,
+}
+
+impl Drop for Outer {
+    fn drop(&mut self) {
+        // 这里字段还没有被drop 仍然可以访问
+        log(&format!("Outer({}, {})", self.first.name(), self.second.name()));
+    }
+}
+
+// 和 main.rs 中的 User 类似 但字段会记录drop
+// 注意实现了Drop的结构体不允许把字段移走 所以 ..u1 这种部分移动只能用在没有实现Drop的结构体上
+struct TracedUser {
+    username: Noisy,
+    email: Noisy,
+    #[allow(dead_code)]
+    sign_in_count: u64,
+}
+
+fn struct_fields() -> Vec<String> {
+    {
+        let _outer = Outer { second: Noisy::new("second"), first: Noisy::new("first") };
+    }
+    // 和初始化时写字段的顺序无关 只和定义时的顺序有关
+    take_log()
+}
+
+fn tuple_and_vec() -> Vec<String> {
+    {
+        let _tuple = (Noisy::new("t.0"), Noisy::new("t.1"), Noisy::new("t.2"));
+        let _v: Vec<Noisy> = (0..3).map(|i| Noisy::new(&format!("v[{}]", i))).collect();
+    }
+    // _v 后声明 先drop 但各自内部都是按下标从小到大
+    take_log()
+}
+
+fn shadowing() -> Vec<String> {
+    {
+        // 对应 main.rs 中的 let x = 5; let x = x + 1; { let x = x * 2; }
+        let x = Noisy::new("x1");
+        let x = Noisy::new(&format!("{}+1", x.name()));
+        {
+            let x = Noisy::new(&format!("{}*2", x.name()));
+            log(&format!("内部作用域中的x = {}", x.name()));
+        }
+        log(&format!("main作用域中的x = {}", x.name()));
+    }
+    // 第一个x被遮蔽后无法再访问 但它仍然活到作用域结束 最后才被drop
+    take_log()
+}
+
+fn borrow_scope() -> Vec<String> {
+    {
+        // 对应 main.rs 中的 { let _r1 = &mut s; } 引用离开作用域时什么都不会释放 只是借用结束了
+        let mut s = Noisy::new("s");
+        {
+            let _r1 = &mut s;
+        }
+        log("_r1 离开作用域");
+        let r2 = &mut s;
+        log(&format!("r2 = {}", r2.name()));
+    }
+    take_log()
+}
+
+fn partial_move() -> Vec<String> {
+    {
+        let u1 = TracedUser { username: Noisy::new("u1.username"), email: Noisy::new("u1.email"), sign_in_count: 1 };
+        let u3 = TracedUser { email: Noisy::new("u3.email"), ..u1 };
+        log(&format!("u3.username = {}", u3.username.name()));
+        // u1.username 已经移动给了u3 但u1.email还属于u1 可以继续使用
+        log(&format!("u1.email = {}", u1.email.name()));
+    }
+    // u3后声明 先drop 然后u1只drop剩下的email字段
+    take_log()
+}
+
+fn temporaries() -> Vec<String> {
+    {
+        // 临时值在这条语句结束时就被drop
+        let len = Noisy::new("temp").name().len();
+        log(&format!("len = {}", len));
+        // let _ = 不会绑定 值立刻被drop 而 let _name = 会一直保留到作用域结束
+        let _ = Noisy::new("underscore");
+        let _kept = Noisy::new("_kept");
+        // 临时值的生命周期被延长到r的作用域结束
+        let r = &Noisy::new("extended");
+        log(&format!("r = {}", r.name()));
+        // 重新赋值时旧值立刻被drop
+        let mut slot = Noisy::new("old");
+        log(&format!("slot = {}", slot.name()));
+        slot = Noisy::new("new");
+        log(&format!("slot = {}", slot.name()));
+        // 提前drop
+        let early = Noisy::new("early");
+        drop(early);
+        log("作用域结束");
+    }
+    take_log()
+}
+
+// 每个场景返回它记录下的drop顺序
+type Case = fn() -> Vec<String>;
+
+pub fn demo() {
+    take_log();
+    let cases: [(&str, Case); 6] = [
+        ("结构体字段", struct_fields),
+        ("元组和Vec", tuple_and_vec),
+        ("变量遮蔽", shadowing),
+        ("借用的作用域", borrow_scope),
+        ("部分移动", partial_move),
+        ("临时值", temporaries),
+    ];
+    for (name, case) in cases {
+        println!("{}: {}", name, case().join(" -> "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn struct_drops_itself_then_fields_in_declaration_order() {
+        take_log();
+        assert_eq!(struct_fields(), ["Outer(first, second)", "first", "second"]);
+    }
+
+    #[test]
+    fn elements_drop_in_index_order() {
+        take_log();
+        assert_eq!(tuple_and_vec(), ["v[0]", "v[1]", "v[2]", "t.0", "t.1", "t.2"]);
+    }
+
+    #[test]
+    fn shadowed_variables_live_to_scope_end() {
+        take_log();
+        assert_eq!(
+            shadowing(),
+            ["内部作用域中的x = x1+1*2", "x1+1*2", "main作用域中的x = x1+1", "x1+1", "x1"]
+        );
+    }
+
+    #[test]
+    fn references_drop_nothing() {
+        take_log();
+        assert_eq!(borrow_scope(), ["_r1 离开作用域", "r2 = s", "s"]);
+    }
+
+    #[test]
+    fn partially_moved_struct_drops_remaining_fields() {
+        take_log();
+        assert_eq!(
+            partial_move(),
+            ["u3.username = u1.username", "u1.email = u1.email", "u1.username", "u3.email", "u1.email"]
+        );
+    }
+
+    #[test]
+    fn temporaries_and_reassignment() {
+        take_log();
+        assert_eq!(
+            temporaries(),
+            [
+                "temp", "len = 4", "underscore", "r = extended", "slot = old", "old", "slot = new", "early", "作用域结束", "new",
+                "extended", "_kept",
+            ]
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::fmt;
+
+// 字符串字面量的解析和生成 规则和Rust源码中的写法一致
+// parse_literal: 把源码中的字面量文本(带引号)解析成它的值 例如 "\"a\\x52\"" => "aR"
+// to_literal: 把任意字符串渲染成最短的合法字面量 需要时使用原始字符串 并选择最少的#号
+
+#[derive(Debug, PartialEq)]
+pub enum LiteralValue {
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LiteralErrorKind {
+    // 缺少开头的引号或者前缀不认识
+    InvalidPrefix,
+    // 没有找到结束的引号
+    Unterminated,
+    // 结束引号之后还有多余的内容
+    TrailingCharacters,
+    UnknownEscape(char),
+    // \x 后面的十六进制不合法 或者在字符串中超过了0x7F
+    InvalidHexEscape,
+    InvalidUnicodeEscape,
+    // 字节字符串中只允许ASCII字符
+    NonAsciiInByteString,
+    // 字面量中不允许出现单独的回车符
+    BareCarriageReturn,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LiteralError {
+    pub kind: LiteralErrorKind,
+    // 出错位置在字面量文本中的字节偏移
+    pub offset: usize,
+}
+
+impl fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match &self.kind {
+            LiteralErrorKind::InvalidPrefix => "字面量必须以 \" r\" b\" 或 br\" 开头".to_string(),
+            LiteralErrorKind::Unterminated => "字面量没有结束的引号".to_string(),
+            LiteralErrorKind::TrailingCharacters => "结束引号之后还有多余的字符".to_string(),
+            LiteralErrorKind::UnknownEscape(c) => format!("未知的转义字符 \\{}", c),
+            LiteralErrorKind::InvalidHexEscape => "\\x 转义不合法".to_string(),
+            LiteralErrorKind::InvalidUnicodeEscape => "\\u{...} 转义不合法".to_string(),
+            LiteralErrorKind::NonAsciiInByteString => "字节字符串中只能出现ASCII字符".to_string(),
+            LiteralErrorKind::BareCarriageReturn => "字面量中不能出现单独的回车符".to_string(),
+        };
+        write!(f, "{} (偏移{})", msg, self.offset)
+    }
+}
+
+impl std::error::Error for LiteralError {}
+
+fn err(kind: LiteralErrorKind, offset: usize) -> LiteralError {
+    LiteralError { kind, offset }
+}
+
+pub fn parse_literal(text: &str) -> Result<LiteralValue, LiteralError> {
+    let (is_bytes, rest) = match text.strip_prefix('b') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let prefix_len = text.len() - rest.len();
+    if let Some(raw) = rest.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body_start = prefix_len + 1 + hashes;
+        if !text[body_start..].starts_with('"') {
+            return Err(err(LiteralErrorKind::InvalidPrefix, body_start));
+        }
+        let body_start = body_start + 1;
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let body_len = text[body_start..]
+            .find(&closing)
+            .ok_or(err(LiteralErrorKind::Unterminated, text.len()))?;
+        let end = body_start + body_len + closing.len();
+        if end != text.len() {
+            return Err(err(LiteralErrorKind::TrailingCharacters, end));
+        }
+        let body = &text[body_start..body_start + body_len];
+        // 原始字符串不处理任何转义 只需要检查回车和非ASCII字符
+        if let Some(i) = body.find('\r') {
+            return Err(err(LiteralErrorKind::BareCarriageReturn, body_start + i));
+        }
+        return if is_bytes {
+            match body.char_indices().find(|(_, c)| !c.is_ascii()) {
+                Some((i, _)) => Err(err(LiteralErrorKind::NonAsciiInByteString, body_start + i)),
+                None => Ok(LiteralValue::Bytes(body.as_bytes().to_vec())),
+            }
+        } else {
+            Ok(LiteralValue::Str(body.to_string()))
+        };
+    }
+    if !rest.starts_with('"') {
+        return Err(err(LiteralErrorKind::InvalidPrefix, prefix_len));
+    }
+    let bytes = unescape(text, prefix_len + 1, is_bytes)?;
+    if is_bytes {
+        Ok(LiteralValue::Bytes(bytes))
+    } else {
+        // 非字节字符串中 \x 只允许0x00~0x7F 所以结果一定是合法的UTF-8
+        Ok(LiteralValue::Str(String::from_utf8(bytes).unwrap_or_default()))
+    }
+}
+
+// 处理普通(非原始)字面量 start是开头引号之后的位置
+fn unescape(text: &str, start: usize, is_bytes: bool) -> Result<Vec<u8>, LiteralError> {
+    let mut out = Vec::new();
+    let mut chars = text[start..].char_indices().map(|(i, c)| (i + start, c)).peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                return match chars.next() {
+                    Some((j, _)) => Err(err(LiteralErrorKind::TrailingCharacters, j)),
+                    None => Ok(out),
+                };
+            }
+            '\r' => return Err(err(LiteralErrorKind::BareCarriageReturn, i)),
+            '\\' => {
+                let (j, e) = chars.next().ok_or(err(LiteralErrorKind::Unterminated, text.len()))?;
+                match e {
+                    'n' => out.push(b'\n'),
+                    'r' => out.push(b'\r'),
+                    't' => out.push(b'\t'),
+                    '\\' => out.push(b'\\'),
+                    '0' => out.push(0),
+                    '\'' => out.push(b'\''),
+                    '"' => out.push(b'"'),
+                    'x' => {
+                        let hex: String = (0..2).filter_map(|_| chars.next().map(|(_, h)| h)).collect();
+                        let value = u8::from_str_radix(&hex, 16)
+                            .ok()
+                            .filter(|&v| hex.len() == 2 && (is_bytes || v <= 0x7F))
+                            .ok_or(err(LiteralErrorKind::InvalidHexEscape, j))?;
+                        out.push(value);
+                    }
+                    'u' if !is_bytes => {
+                        let invalid = err(LiteralErrorKind::InvalidUnicodeEscape, j);
+                        if chars.next().map(|(_, b)| b) != Some('{') {
+                            return Err(invalid);
+                        }
+                        let mut hex = String::new();
+                        loop {
+                            match chars.next() {
+                                Some((_, '}')) => break,
+                                Some((_, '_')) => {}
+                                Some((_, h)) if h.is_ascii_hexdigit() && hex.len() < 6 => hex.push(h),
+                                _ => return Err(invalid),
+                            }
+                        }
+                        let ch = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or(invalid)?;
+                        out.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                    // 行尾的\ 会把换行符和下一行开头的空白一起忽略
+                    '\n' => {
+                        while let Some(&(_, w)) = chars.peek() {
+                            if !matches!(w, ' ' | '\t' | '\n' | '\r') {
+                                break;
+                            }
+                            chars.next();
+                        }
+                    }
+                    other => return Err(err(LiteralErrorKind::UnknownEscape(other), j)),
+                }
+            }
+            c if is_bytes && !c.is_ascii() => return Err(err(LiteralErrorKind::NonAsciiInByteString, i)),
+            c => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    Err(err(LiteralErrorKind::Unterminated, text.len()))
+}
+
+// 原始字符串需要的最少#号个数: 比内容中任何一个 " 后面紧跟的#号都多一个
+fn raw_hashes(body: &str) -> usize {
+    body.match_indices('"')
+        .map(|(i, _)| body[i + 1..].len() - body[i + 1..].trim_start_matches('#').len() + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+// 总是写成带转义的普通字符串字面量 结果只有一行
+pub fn escape_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0' => out.push_str("\\0"),
+            c if (c as u32) < 0x80 && c.is_ascii_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn to_literal(s: &str) -> String {
+    let escaped = escape_str(s);
+    // 原始字符串中不能出现回车 控制字符虽然合法但是不可见 也不用原始字符串
+    if s.chars().any(|c| c.is_control() && c != '\n' && c != '\t') {
+        return escaped;
+    }
+    let hashes = "#".repeat(raw_hashes(s));
+    let raw = format!("r{}\"{}\"{}", hashes, s, hashes);
+    if raw.len() < escaped.len() {
+        raw
+    } else {
+        escaped
+    }
+}
+
+pub fn to_byte_literal(bytes: &[u8]) -> String {
+    let mut escaped = String::from("b\"");
+    for &b in bytes {
+        match b {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            b'\n' => escaped.push_str("\\n"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\t' => escaped.push_str("\\t"),
+            b'\0' => escaped.push_str("\\0"),
+            0x20..=0x7E => escaped.push(b as char),
+            _ => escaped.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    escaped.push('"');
+    // 只有全部是可打印ASCII(以及换行和制表符)时才能写成原始字节字符串
+    match std::str::from_utf8(bytes) {
+        Ok(s) if s.bytes().all(|b| matches!(b, 0x20..=0x7E | b'\n' | b'\t')) => {
+            let hashes = "#".repeat(raw_hashes(s));
+            let raw = format!("br{}\"{}\"{}", hashes, s, hashes);
+            if raw.len() < escaped.len() {
+                raw
+            } else {
+                escaped
+            }
+        }
+        _ => escaped,
+    }
+}
+
+pub fn demo() {
+    // 上面课程中出现的几种字面量 解析结果和编译器的结果完全一致
+    for text in [r#""hello world \x52\x75\x73\x74\n""#, r#""\u{211D}""#, r#"r"Escapes don't work here: \x3F \u{211D}""#, r#"b"\xFF\x00a""#] {
+        println!("{} => {:?}", text, parse_literal(text));
+    }
+
+    // 错误会指出具体的位置
+    for bad in [r#""\xFF""#, r#""\q""#, r#""abc"#, r#"b"你""#, r##"r#"abc""##, r#""\u{D800}""#] {
+        match parse_literal(bad) {
+            Ok(v) => println!("{} => {:?}", bad, v),
+            Err(e) => println!("{} => {}", bad, e),
+        }
+    }
+
+    // 生成最短的字面量
+    for s in ["hello", r#"C:\path\to\file"#, r##"A string with "# in it"##, r###"A string with "# in it. And even "##!"###] {
+        println!("{}", to_literal(s));
+    }
+    println!("{}", to_byte_literal(&[0xFF, b'a']));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+
+    #[test]
+    fn parses_lesson_literals() {
+        let cases = [
+            (r#""hello world \x52\x75\x73\x74\n""#, "hello world \x52\x75\x73\x74\n"),
+            (r#""\u{211D}""#, "\u{211D}"),
+            ("\"String literals\\\n        can span\"", "String literalscan span"),
+            (r#"r"Escapes don't work here: \x3F \u{211D}""#, r"Escapes don't work here: \x3F \u{211D}"),
+            (r###"r#"And then I said: "There is no escape!""#"###, r#"And then I said: "There is no escape!""#),
+            (r####"r###"A string with "# in it. And even "##!"###"####, r###"A string with "# in it. And even "##!"###),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_literal(text), Ok(LiteralValue::Str(expected.to_string())));
+        }
+        assert_eq!(parse_literal(r#"b"\xFF\x00a""#), Ok(LiteralValue::Bytes(vec![0xFF, 0, b'a'])));
+        assert_eq!(parse_literal(r##"br#"a"b"#"##), Ok(LiteralValue::Bytes(b"a\"b".to_vec())));
+    }
+
+    #[test]
+    fn reports_error_kind() {
+        let cases = [
+            (r#""\xFF""#, LiteralErrorKind::InvalidHexEscape),
+            (r#""\q""#, LiteralErrorKind::UnknownEscape('q')),
+            (r#""abc"#, LiteralErrorKind::Unterminated),
+            (r#"b"你""#, LiteralErrorKind::NonAsciiInByteString),
+            (r##"r#"abc""##, LiteralErrorKind::Unterminated),
+            (r#""\u{D800}""#, LiteralErrorKind::InvalidUnicodeEscape),
+            (r#""a"b"#, LiteralErrorKind::TrailingCharacters),
+            ("x", LiteralErrorKind::InvalidPrefix),
+        ];
+        for (text, kind) in cases {
+            assert_eq!(parse_literal(text).map_err(|e| e.kind), Err(kind), "{}", text);
+        }
+    }
+
+    #[test]
+    fn renders_shortest_literal() {
+        assert_eq!(to_literal("hello"), "\"hello\"");
+        assert_eq!(to_literal(r#"C:\path\to\file"#), r#"r"C:\path\to\file""#);
+        assert_eq!(to_literal(r#""a" "b" \c"#), r##"r#""a" "b" \c"#"##);
+        // 只有一个引号时 转义比加#号更短
+        assert_eq!(to_literal(r##"A string with "# in it"##), r##""A string with \"# in it""##);
+        assert_eq!(to_literal("tab\tcr\r"), r#""tab\tcr\r""#);
+        assert_eq!(to_byte_literal(&[0xFF, b'a']), r#"b"\xffa""#);
+    }
+
+    // 往返测试: 任意字符串渲染成字面量再解析回来 结果应该不变
+    #[test]
+    fn round_trips_random_strings() {
+        let mut rng = XorShift::new(29);
+        let tricky = ['"', '#', '\\', 'r', '\n', '\r', '\t', '\0', '\u{7}', '\u{85}', '{', '}'];
+        for _ in 0..1000 {
+            let len = rng.below(12);
+            let s: String = (0..len)
+                .map(|_| if rng.below(2) == 0 { tricky[rng.below(tricky.len())] } else { rng.next_char() })
+                .collect();
+            let literal = to_literal(&s);
+            assert_eq!(parse_literal(&literal), Ok(LiteralValue::Str(s.clone())), "literal = {}", literal);
+
+            let bytes: Vec<u8> = (0..len).map(|_| rng.next_u64() as u8).collect();
+            let literal = to_byte_literal(&bytes);
+            assert_eq!(parse_literal(&literal), Ok(LiteralValue::Bytes(bytes)), "literal = {}", literal);
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt;
+use std::hint::black_box;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::unicode::{pad, Align};
+
+// 间隙缓冲区(gap buffer) 文本编辑器常用的数据结构
+// String 底层是一个连续的 Vec<u8> 在开头插入时 后面所有的字节都要向后移动一次 所以反复在前面插入是O(n)的
+// 间隙缓冲区在光标位置留出一段空白(gap) 在同一位置附近连续编辑时只需要移动很少的字节
+//
+//   [h e l l o _ _ _ _ w o r l d]
+//              ^gap_start ^gap_end
+//
+// 对外的接口和String保持一致 下标也是字节下标 区别是下标不在字符边界上时返回Err 而不是panic
+
+#[derive(Debug, PartialEq)]
+pub enum GapError {
+    OutOfBounds { index: usize, len: usize },
+    NotCharBoundary(usize),
+    // 范围的起点在终点后面
+    InvalidRange { start: usize, end: usize },
+}
+
+impl fmt::Display for GapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GapError::OutOfBounds { index, len } => write!(f, "下标{}越界 文本长度为{}", index, len),
+            GapError::NotCharBoundary(index) => write!(f, "下标{}不在字符边界上", index),
+            GapError::InvalidRange { start, end } => write!(f, "范围{}..{}的起点在终点后面", start, end),
+        }
+    }
+}
+
+impl std::error::Error for GapError {}
+
+pub struct GapBuffer {
+    // gap_start..gap_end 之间的字节是未使用的 其余部分始终是合法的UTF-8
+    buf: Vec<u8>,
+    gap_start: usize,
+    gap_end: usize,
+}
+
+const MIN_GAP: usize = 16;
+
+impl GapBuffer {
+    pub fn new() -> GapBuffer {
+        GapBuffer { buf: Vec::new(), gap_start: 0, gap_end: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len() - self.gap_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn gap_len(&self) -> usize {
+        self.gap_end - self.gap_start
+    }
+
+    // 逻辑下标(不含间隙)对应的字节
+    fn byte_at(&self, index: usize) -> u8 {
+        if index < self.gap_start {
+            self.buf[index]
+        } else {
+            self.buf[index + self.gap_len()]
+        }
+    }
+
+    pub fn is_char_boundary(&self, index: usize) -> bool {
+        // UTF-8中 形如0b10xxxxxx的字节是多字节字符的后续字节 不能作为字符的开头
+        index == self.len() || (index < self.len() && self.byte_at(index) & 0xC0 != 0x80)
+    }
+
+    fn check(&self, index: usize) -> Result<(), GapError> {
+        if index > self.len() {
+            Err(GapError::OutOfBounds { index, len: self.len() })
+        } else if !self.is_char_boundary(index) {
+            Err(GapError::NotCharBoundary(index))
+        } else {
+            Ok(())
+        }
+    }
+
+    // 把间隙移动到index处 只需要搬动间隙和index之间的字节
+    fn move_gap(&mut self, index: usize) {
+        if index < self.gap_start {
+            let n = self.gap_start - index;
+            self.buf.copy_within(index..self.gap_start, self.gap_end - n);
+            self.gap_start -= n;
+            self.gap_end -= n;
+        } else if index > self.gap_start {
+            let n = index - self.gap_start;
+            self.buf.copy_within(self.gap_end..self.gap_end + n, self.gap_start);
+            self.gap_start += n;
+            self.gap_end += n;
+        }
+    }
+
+    // 保证间隙至少有additional个字节 不够时把缓冲区扩大一倍 和Vec的扩容策略类似
+    fn reserve(&mut self, additional: usize) {
+        if self.gap_len() >= additional {
+            return;
+        }
+        let new_cap = (self.buf.len() * 2).max(self.len() + additional + MIN_GAP);
+        let grow = new_cap - self.buf.len();
+        let tail = self.buf.len() - self.gap_end;
+        self.buf.resize(new_cap, 0);
+        self.buf.copy_within(self.gap_end..self.gap_end + tail, self.gap_end + grow);
+        self.gap_end += grow;
+    }
+
+    fn insert_bytes(&mut self, index: usize, bytes: &[u8]) {
+        self.move_gap(index);
+        self.reserve(bytes.len());
+        self.buf[self.gap_start..self.gap_start + bytes.len()].copy_from_slice(bytes);
+        self.gap_start += bytes.len();
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.insert_bytes(self.len(), s.as_bytes());
+    }
+
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]));
+    }
+
+    pub fn insert_str(&mut self, index: usize, s: &str) -> Result<(), GapError> {
+        self.check(index)?;
+        self.insert_bytes(index, s.as_bytes());
+        Ok(())
+    }
+
+    pub fn insert(&mut self, index: usize, ch: char) -> Result<(), GapError> {
+        self.insert_str(index, ch.encode_utf8(&mut [0; 4]))
+    }
+
+    // 删除range范围的字节 把间隙移到range.start 然后直接扩大间隙即可
+    fn delete(&mut self, range: Range<usize>) {
+        self.move_gap(range.start);
+        self.gap_end += range.end - range.start;
+    }
+
+    pub fn replace_range(&mut self, range: Range<usize>, replace_with: &str) -> Result<(), GapError> {
+        self.check(range.start)?;
+        self.check(range.end)?;
+        if range.start > range.end {
+            return Err(GapError::InvalidRange { start: range.start, end: range.end });
+        }
+        let start = range.start;
+        self.delete(range);
+        self.insert_bytes(start, replace_with.as_bytes());
+        Ok(())
+    }
+
+    // 从index开始的那个字符
+    fn char_at(&self, index: usize) -> Option<char> {
+        let len = (1..=4).find(|&n| self.is_char_boundary(index + n))?;
+        let bytes: Vec<u8> = (index..index + len).map(|i| self.byte_at(i)).collect();
+        std::str::from_utf8(&bytes).ok()?.chars().next()
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<char, GapError> {
+        self.check(index)?;
+        let ch = self.char_at(index).ok_or(GapError::OutOfBounds { index, len: self.len() })?;
+        self.delete(index..index + ch.len_utf8());
+        Ok(ch)
+    }
+
+    pub fn pop(&mut self) -> Option<char> {
+        let last = (0..self.len()).rev().find(|&i| self.is_char_boundary(i))?;
+        self.remove(last).ok()
+    }
+
+    pub fn truncate(&mut self, new_len: usize) -> Result<(), GapError> {
+        if new_len >= self.len() {
+            return Ok(());
+        }
+        self.check(new_len)?;
+        self.delete(new_len..self.len());
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.gap_start = 0;
+        self.gap_end = self.buf.len();
+    }
+
+    // 把间隙移到末尾 这样所有文本都是连续的 可以直接借用成&str
+    pub fn make_contiguous(&mut self) -> &str {
+        self.move_gap(self.len());
+        // 间隙只会停在字符边界上 所以间隙前面一定是合法的UTF-8
+        std::str::from_utf8(&self.buf[..self.gap_start]).unwrap_or_default()
+    }
+
+    // 间隙两侧的文本 都是合法的UTF-8
+    fn halves(&self) -> (&str, &str) {
+        let front = std::str::from_utf8(&self.buf[..self.gap_start]).unwrap_or_default();
+        let back = std::str::from_utf8(&self.buf[self.gap_end..]).unwrap_or_default();
+        (front, back)
+    }
+}
+
+impl Default for GapBuffer {
+    fn default() -> Self {
+        GapBuffer::new()
+    }
+}
+
+impl From<&str> for GapBuffer {
+    fn from(s: &str) -> GapBuffer {
+        let mut buffer = GapBuffer::new();
+        buffer.push_str(s);
+        buffer
+    }
+}
+
+impl fmt::Display for GapBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (front, back) = self.halves();
+        write!(f, "{}{}", front, back)
+    }
+}
+
+impl fmt::Debug for GapBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string(), f)
+    }
+}
+
+pub fn demo() {
+    // 和 main.rs 中String的操作一一对应
+    let mut s = GapBuffer::from("hello");
+    s.push_str(" world");
+    s.push('!');
+    println!("s = {}", s);
+    let _ = s.insert_str(0, "hello Rust");
+    let _ = s.insert(10, '!');
+    println!("s = {}", s);
+    let _ = s.replace_range(0..5, "HELLO");
+    println!("s = {:?}", s);
+    println!("p1 = {:?}", s.pop());
+
+    let mut s = GapBuffer::from("你好你好");
+    println!("remove(0) = {:?}", s.remove(0));
+    // String::remove(2) 会panic 这里返回错误
+    if let Err(err) = s.remove(2) {
+        println!("remove(2): {}", err);
+    }
+    let _ = s.truncate(3);
+    println!("s = {}", s.make_contiguous());
+    s.clear();
+    println!("clear之后 is_empty = {}", s.is_empty());
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+// 对比String和GapBuffer 在开头反复插入、在中间反复编辑时的耗时
+// cargo run --release -- bench-text [次数]
+pub fn bench(n: usize) {
+    // 表头有中文 用按显示宽度填充的pad 而不是{:<24}
+    println!("{}{:>14}{:>14}", pad("场景", 24, Align::Left), "String", "GapBuffer");
+
+    let string = time(|| {
+        let mut s = String::new();
+        for _ in 0..n {
+            s.insert_str(0, "字a");
+        }
+        black_box(&s);
+    });
+    let gap = time(|| {
+        let mut s = GapBuffer::new();
+        for _ in 0..n {
+            let _ = s.insert_str(0, "字a");
+        }
+        black_box(&s);
+    });
+    println!("{:<24}{:>14?}{:>14?}", format!("front insert x{}", n), string, gap);
+
+    // 在文本中间附近做插入和删除 光标每次移动一点点 这是编辑器里最常见的情况
+    let base = "hello 世界 ".repeat(n / 8 + 1);
+    let string = time(|| {
+        let mut s = base.clone();
+        let mut cursor = s.len() / 2;
+        for i in 0..n {
+            while !s.is_char_boundary(cursor) {
+                cursor += 1;
+            }
+            if i % 3 == 2 {
+                s.remove(cursor);
+            } else {
+                s.insert(cursor, 'x');
+                cursor += 1;
+            }
+        }
+        black_box(&s);
+    });
+    let gap = time(|| {
+        let mut s = GapBuffer::from(base.as_str());
+        let mut cursor = s.len() / 2;
+        for i in 0..n {
+            while !s.is_char_boundary(cursor) {
+                cursor += 1;
+            }
+            if i % 3 == 2 {
+                let _ = s.remove(cursor);
+            } else {
+                let _ = s.insert(cursor, 'x');
+                cursor += 1;
+            }
+        }
+        black_box(&s);
+    });
+    println!("{:<24}{:>14?}{:>14?}", format!("mid edit x{}", n), string, gap);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+
+    #[test]
+    fn mirrors_string_lesson() {
+        let mut s = GapBuffer::from("hello");
+        s.push_str(" world");
+        s.push('!');
+        assert_eq!(s.insert_str(0, "hello Rust"), Ok(()));
+        assert_eq!(s.insert(10, '!'), Ok(()));
+        assert_eq!(s.to_string(), "hello Rust!hello world!");
+        assert_eq!(s.replace_range(0..5, "HELLO"), Ok(()));
+        assert_eq!(s.pop(), Some('!'));
+        assert_eq!(s.to_string(), "HELLO Rust!hello world");
+    }
+
+    #[test]
+    fn reports_errors_instead_of_panicking() {
+        let mut s = GapBuffer::from("你好你好");
+        assert_eq!(s.remove(0), Ok('你'));
+        assert_eq!(s.remove(2), Err(GapError::NotCharBoundary(2)));
+        assert_eq!(s.truncate(3), Ok(()));
+        assert_eq!(s.make_contiguous(), "好");
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 3..0;
+        assert_eq!(s.replace_range(reversed, "x"), Err(GapError::InvalidRange { start: 3, end: 0 }));
+        s.clear();
+        assert!(s.is_empty());
+        assert_eq!(s.insert(1, 'a'), Err(GapError::OutOfBounds { index: 1, len: 0 }));
+    }
+
+    // 随机编辑 结果应该始终和String一致
+    #[test]
+    fn matches_string_model() {
+        let mut rng = XorShift::new(30);
+        let mut expected = String::new();
+        let mut s = GapBuffer::new();
+        for _ in 0..2000 {
+            let index = rng.below(expected.len() + 1);
+            let ok = expected.is_char_boundary(index);
+            match rng.below(4) {
+                0 => {
+                    let text = rng.next_string(3);
+                    assert_eq!(s.insert_str(index, &text).is_ok(), ok);
+                    if ok {
+                        expected.insert_str(index, &text);
+                    }
+                }
+                1 if index < expected.len() => {
+                    assert_eq!(s.remove(index).ok(), if ok { Some(expected.remove(index)) } else { None });
+                }
+                2 => {
+                    let end = (index + rng.below(4)).min(expected.len());
+                    let ok = ok && expected.is_char_boundary(end);
+                    assert_eq!(s.replace_range(index..end, "é").is_ok(), ok);
+                    if ok {
+                        expected.replace_range(index..end, "é");
+                    }
+                }
+                _ => assert_eq!(s.pop(), expected.pop()),
+            }
+            assert_eq!(s.to_string(), expected);
+        }
+    }
+}
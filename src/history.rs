@@ -0,0 +1,212 @@
+use std::collections::VecDeque;
+
+use crate::turtle::{Canvas, CanvasError, Rgb};
+use crate::Action;
+
+// 在画布上执行Action的撤销/重做历史
+// 每个动作执行前记录它的逆操作: Say 撤销时删掉最后一句话 MoveTo 记住原来的笔的位置和被覆盖的像素
+// ChangeColor 记住原来的颜色 撤销就是执行逆操作 重做就是重新执行原来的Action
+// 撤销之后再执行新的动作 重做栈会被清空(历史出现了分支 旧的那一支被丢掉)
+// 历史最多保存capacity步 超出时最早的一步被丢掉 它已经生效 只是不能再撤销
+
+// 逆操作 由 Canvas::execute_undoable 生成 交给 Canvas::revert 执行
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inverse {
+    Unsay,
+    // pixels是直线经过的像素在画布中的下标和原来的值
+    MoveBack { pen: (i32, i32), pixels: Vec<(usize, Option<Rgb>)> },
+    RestoreColor(Rgb),
+}
+
+struct Entry {
+    action: Action,
+    inverse: Inverse,
+}
+
+pub struct History {
+    canvas: Canvas,
+    done: VecDeque<Entry>,
+    undone: Vec<Action>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(canvas: Canvas, capacity: usize) -> History {
+        History { canvas, done: VecDeque::new(), undone: Vec::new(), capacity }
+    }
+
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    fn record(&mut self, action: Action) -> Result<(), CanvasError> {
+        let inverse = self.canvas.execute_undoable(&action)?;
+        self.done.push_back(Entry { action, inverse });
+        if self.done.len() > self.capacity {
+            self.done.pop_front();
+        }
+        Ok(())
+    }
+
+    // 执行失败时画布没有变化 也不会清空重做栈
+    pub fn execute(&mut self, action: Action) -> Result<(), CanvasError> {
+        self.record(action)?;
+        self.undone.clear();
+        Ok(())
+    }
+
+    // 返回被撤销的动作 没有可以撤销的动作时返回None
+    pub fn undo(&mut self) -> Option<&Action> {
+        let Entry { action, inverse } = self.done.pop_back()?;
+        self.canvas.revert(inverse);
+        self.undone.push(action);
+        self.undone.last()
+    }
+
+    // 重新执行最近一次被撤销的动作 它执行成功过 所以这里不会失败
+    pub fn redo(&mut self) -> Option<&Action> {
+        let action = self.undone.pop()?;
+        self.record(action).ok()?;
+        self.done.back().map(|entry| &entry.action)
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.done.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.undone.len()
+    }
+}
+
+pub fn demo() {
+    let mut history = History::new(Canvas::new(4, 3), 10);
+    for action in [Action::MoveTo(3, 0), Action::ChangeColor(255, 255, 0), Action::Say(String::from("转弯")), Action::MoveTo(3, 2)] {
+        let _ = history.execute(action);
+    }
+    print!("{}", history.canvas().to_ascii());
+    // 撤销MoveTo 笔回到原来的位置 被覆盖的像素恢复原来的值
+    for _ in 0..3 {
+        if let Some(action) = history.undo() {
+            println!("撤销 {:?}", action);
+        }
+    }
+    print!("{}", history.canvas().to_ascii());
+    if let Some(action) = history.redo() {
+        println!("重做 {:?}", action);
+    }
+    // 执行新的动作 剩下的重做被丢掉
+    let _ = history.execute(Action::MoveTo(0, 2));
+    println!("可以撤销{}步 可以重做{}步", history.undo_len(), history.redo_len());
+    print!("{}", history.canvas().to_ascii());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+
+    fn random_action(rng: &mut XorShift) -> Action {
+        let coord = |rng: &mut XorShift| rng.below(14) as i32 - 2;
+        match rng.below(8) {
+            0 => Action::Say(rng.next_string(4)),
+            1 | 2 => {
+                // 偶尔出现不合法的颜色分量
+                let c = |rng: &mut XorShift| if rng.below(20) == 0 { 256 } else { rng.below(256) as i32 };
+                Action::ChangeColor(c(rng), c(rng), c(rng))
+            }
+            _ => Action::MoveTo(coord(rng), coord(rng)),
+        }
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut history = History::new(Canvas::new(4, 3), 10);
+        history.execute(Action::MoveTo(3, 0)).unwrap();
+        history.execute(Action::ChangeColor(255, 255, 0)).unwrap();
+        history.execute(Action::Say(String::from("转弯"))).unwrap();
+        history.execute(Action::MoveTo(3, 2)).unwrap();
+        assert_eq!(history.canvas().to_ascii(), "###-\n...-\n...-\n");
+
+        // 撤销MoveTo 笔回到原来的位置 被覆盖的(3, 0)恢复成黑色
+        assert_eq!(history.undo(), Some(&Action::MoveTo(3, 2)));
+        assert_eq!(history.canvas().to_ascii(), "####\n....\n....\n");
+        assert_eq!(history.canvas().pen(), (3, 0));
+        assert_eq!(history.undo(), Some(&Action::Say(String::from("转弯"))));
+        assert!(history.canvas().speech().is_empty());
+        assert_eq!(history.undo(), Some(&Action::ChangeColor(255, 255, 0)));
+        assert_eq!(history.canvas().color(), Rgb::BLACK);
+        assert_eq!((history.undo_len(), history.redo_len()), (1, 3));
+
+        assert_eq!(history.redo(), Some(&Action::ChangeColor(255, 255, 0)));
+        assert_eq!(history.canvas().color(), Rgb(255, 255, 0));
+        // 执行新的动作 剩下的两步重做被丢掉
+        history.execute(Action::MoveTo(0, 2)).unwrap();
+        assert_eq!(history.redo_len(), 0);
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.canvas().to_ascii(), "###-\n.--.\n-...\n");
+        // 不合法的动作不会进入历史
+        assert!(history.execute(Action::ChangeColor(-1, 0, 0)).is_err());
+        assert_eq!(history.undo_len(), 3);
+    }
+
+    // 超过容量时最早的一步不能再撤销
+    #[test]
+    fn bounded_capacity() {
+        let mut bounded = History::new(Canvas::new(4, 1), 2);
+        for x in 1..4 {
+            bounded.execute(Action::MoveTo(x, 0)).unwrap();
+        }
+        assert!(bounded.undo().is_some() && bounded.undo().is_some());
+        assert_eq!(bounded.undo(), None);
+        assert_eq!(bounded.canvas().pen(), (1, 0));
+        assert_eq!(bounded.canvas().to_ascii(), "##..\n");
+    }
+
+    // 和重放模型对比: 模型只记录当前生效的动作 从空白画布重新执行一遍 结果必须和历史中的画布完全相同
+    #[test]
+    fn matches_replay_model() {
+        let mut rng = XorShift::new(50);
+        for capacity in [1, 3, 16] {
+            let mut history = History::new(Canvas::new(10, 10), capacity);
+            let mut applied: Vec<Action> = Vec::new();
+            let mut redo: Vec<Action> = Vec::new();
+            let mut undoable = 0;
+            for _ in 0..600 {
+                match rng.below(5) {
+                    0 => {
+                        let undone = history.undo().cloned();
+                        if undoable > 0 {
+                            let action = applied.pop().unwrap();
+                            assert_eq!(undone.as_ref(), Some(&action));
+                            redo.push(action);
+                            undoable -= 1;
+                        } else {
+                            assert_eq!(undone, None);
+                        }
+                    }
+                    1 => {
+                        let redone = history.redo().cloned();
+                        assert_eq!(redone, redo.pop());
+                        if let Some(action) = redone {
+                            applied.push(action);
+                            undoable = (undoable + 1).min(capacity);
+                        }
+                    }
+                    _ => {
+                        let action = random_action(&mut rng);
+                        if history.execute(action.clone()).is_ok() {
+                            applied.push(action);
+                            undoable = (undoable + 1).min(capacity);
+                            redo.clear();
+                        }
+                    }
+                }
+                let mut replay = Canvas::new(10, 10);
+                replay.run(&applied).unwrap();
+                assert_eq!(history.canvas(), &replay);
+                assert_eq!((history.undo_len(), history.redo_len()), (undoable, redo.len()));
+            }
+        }
+    }
+}
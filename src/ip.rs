@@ -0,0 +1,427 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{IpAddr, IpAddrKind, IpInfo};
+
+// IP地址的解析和格式化 IpAddr 不再保存任意字符串 而是保存解析后的4个字节或8个16位的段
+// 解析: IPv4只接受点分十进制 a.b.c.d 每段0到255 不允许前导0(010在一些系统中会被当成八进制)
+//       IPv6支持 :: 压缩和末尾内嵌的IPv4 例如 ::ffff:192.0.2.1 不支持 %eth0 这样的zone id
+// 格式化: IPv6按照RFC 5952输出规范形式 小写、去掉前导0、把最长的一段连续的0压缩成::(长度至少为2 一样长时取第一段)
+//         IPv4映射地址 ::ffff:a.b.c.d 的最后32位用点分十进制输出
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpParseError {
+    Empty,
+    InvalidChar(char),
+    // IPv4地址的段数不是4
+    Ipv4PartCount(usize),
+    // 不是0到255之间的十进制数 或者有前导0
+    Ipv4Octet(String),
+    // 没有使用::时段数必须是8 使用::时最多7段
+    Ipv6SegmentCount(usize),
+    // 不是1到4位的十六进制数
+    Ipv6Segment(String),
+    // 出现了不止一次::
+    DoubleColon,
+    // 内嵌的IPv4只能出现在最后
+    EmbeddedIpv4,
+}
+
+impl fmt::Display for IpParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpParseError::Empty => write!(f, "地址为空"),
+            IpParseError::InvalidChar(ch) => write!(f, "地址中不能出现字符 {:?}", ch),
+            IpParseError::Ipv4PartCount(n) => write!(f, "IPv4地址应该有4段 实际有{}段", n),
+            IpParseError::Ipv4Octet(part) => write!(f, "{:?} 不是0到255之间的十进制数(不能有前导0)", part),
+            IpParseError::Ipv6SegmentCount(n) => write!(f, "IPv6地址的段数不对 共{}段", n),
+            IpParseError::Ipv6Segment(part) => write!(f, "{:?} 不是1到4位的十六进制数", part),
+            IpParseError::DoubleColon => write!(f, "::最多只能出现一次"),
+            IpParseError::EmbeddedIpv4 => write!(f, "内嵌的IPv4地址只能出现在最后"),
+        }
+    }
+}
+
+impl std::error::Error for IpParseError {}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ipv4 {
+    octets: [u8; 4],
+}
+
+impl Ipv4 {
+    pub const LOCALHOST: Ipv4 = Ipv4::new(127, 0, 0, 1);
+    pub const UNSPECIFIED: Ipv4 = Ipv4::new(0, 0, 0, 0);
+
+    pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Ipv4 {
+        Ipv4 { octets: [a, b, c, d] }
+    }
+
+    pub fn octets(&self) -> [u8; 4] {
+        self.octets
+    }
+
+    // 转换成32位整数 方便做掩码运算 例如 10.0.0.1 => 0x0a000001
+    pub fn to_bits(self) -> u32 {
+        u32::from_be_bytes(self.octets)
+    }
+
+    pub fn from_bits(bits: u32) -> Ipv4 {
+        Ipv4 { octets: bits.to_be_bytes() }
+    }
+}
+
+impl FromStr for Ipv4 {
+    type Err = IpParseError;
+
+    fn from_str(s: &str) -> Result<Ipv4, IpParseError> {
+        if s.is_empty() {
+            return Err(IpParseError::Empty);
+        }
+        if let Some(ch) = s.chars().find(|c| !c.is_ascii_digit() && *c != '.') {
+            return Err(IpParseError::InvalidChar(ch));
+        }
+        let parts: Vec<&str> = s.split('.').collect();
+        if parts.len() != 4 {
+            return Err(IpParseError::Ipv4PartCount(parts.len()));
+        }
+        let mut octets = [0u8; 4];
+        for (octet, part) in octets.iter_mut().zip(&parts) {
+            if part.is_empty() || (part.len() > 1 && part.starts_with('0')) {
+                return Err(IpParseError::Ipv4Octet(part.to_string()));
+            }
+            *octet = part.parse().map_err(|_| IpParseError::Ipv4Octet(part.to_string()))?;
+        }
+        Ok(Ipv4 { octets })
+    }
+}
+
+impl fmt::Display for Ipv4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.octets;
+        // 先拼成字符串再用pad输出 这样 {:>15} 这样的宽度和对齐也能生效
+        f.pad(&format!("{}.{}.{}.{}", a, b, c, d))
+    }
+}
+
+// Debug也输出点分十进制 比 Ipv4 { octets: [127, 0, 0, 1] } 更容易阅读
+impl fmt::Debug for Ipv4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ipv6 {
+    segments: [u16; 8],
+}
+
+impl Ipv6 {
+    pub const LOCALHOST: Ipv6 = Ipv6::new([0, 0, 0, 0, 0, 0, 0, 1]);
+    pub const UNSPECIFIED: Ipv6 = Ipv6::new([0; 8]);
+
+    pub const fn new(segments: [u16; 8]) -> Ipv6 {
+        Ipv6 { segments }
+    }
+
+    pub fn segments(&self) -> [u16; 8] {
+        self.segments
+    }
+
+    pub fn to_bits(self) -> u128 {
+        self.segments.iter().fold(0, |bits, &s| (bits << 16) | s as u128)
+    }
+
+    pub fn from_bits(bits: u128) -> Ipv6 {
+        let mut segments = [0u16; 8];
+        for (i, segment) in segments.iter_mut().enumerate() {
+            *segment = (bits >> (16 * (7 - i))) as u16;
+        }
+        Ipv6 { segments }
+    }
+
+    // ::ffff:a.b.c.d 形式的IPv4映射地址
+    pub fn to_ipv4_mapped(self) -> Option<Ipv4> {
+        match self.segments {
+            [0, 0, 0, 0, 0, 0xffff, hi, lo] => {
+                let [a, b] = hi.to_be_bytes();
+                let [c, d] = lo.to_be_bytes();
+                Some(Ipv4::new(a, b, c, d))
+            }
+            _ => None,
+        }
+    }
+}
+
+// 把用:分隔的若干段解析成16位的值 最后一段可以是内嵌的IPv4 占两段
+fn parse_segments(parts: &[&str], is_last: bool) -> Result<Vec<u16>, IpParseError> {
+    let mut segments = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        if part.contains('.') {
+            if !is_last || i + 1 != parts.len() {
+                return Err(IpParseError::EmbeddedIpv4);
+            }
+            let [a, b, c, d] = part.parse::<Ipv4>()?.octets();
+            segments.push(u16::from_be_bytes([a, b]));
+            segments.push(u16::from_be_bytes([c, d]));
+        } else if part.is_empty() || part.len() > 4 {
+            return Err(IpParseError::Ipv6Segment(part.to_string()));
+        } else {
+            segments.push(u16::from_str_radix(part, 16).map_err(|_| IpParseError::Ipv6Segment(part.to_string()))?);
+        }
+    }
+    Ok(segments)
+}
+
+// ::两侧的部分 空字符串表示没有任何段
+fn split_parts(s: &str) -> Vec<&str> {
+    if s.is_empty() {
+        Vec::new()
+    } else {
+        s.split(':').collect()
+    }
+}
+
+impl FromStr for Ipv6 {
+    type Err = IpParseError;
+
+    fn from_str(s: &str) -> Result<Ipv6, IpParseError> {
+        if s.is_empty() {
+            return Err(IpParseError::Empty);
+        }
+        if let Some(ch) = s.chars().find(|c| !c.is_ascii_hexdigit() && *c != ':' && *c != '.') {
+            return Err(IpParseError::InvalidChar(ch));
+        }
+        let mut segments = [0u16; 8];
+        match s.split_once("::") {
+            Some((head, tail)) => {
+                if tail.contains("::") {
+                    return Err(IpParseError::DoubleColon);
+                }
+                // head后面紧跟着:: 所以内嵌的IPv4只能出现在tail的最后
+                let head = parse_segments(&split_parts(head), false)?;
+                let tail = parse_segments(&split_parts(tail), true)?;
+                // ::至少代表一段0
+                if head.len() + tail.len() > 7 {
+                    return Err(IpParseError::Ipv6SegmentCount(head.len() + tail.len()));
+                }
+                segments[..head.len()].copy_from_slice(&head);
+                segments[8 - tail.len()..].copy_from_slice(&tail);
+            }
+            None => {
+                let parts: Vec<&str> = s.split(':').collect();
+                let parsed = parse_segments(&parts, true)?;
+                if parsed.len() != 8 {
+                    return Err(IpParseError::Ipv6SegmentCount(parsed.len()));
+                }
+                segments.copy_from_slice(&parsed);
+            }
+        }
+        Ok(Ipv6 { segments })
+    }
+}
+
+impl fmt::Display for Ipv6 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(v4) = self.to_ipv4_mapped() {
+            return f.pad(&format!("::ffff:{}", v4));
+        }
+        // 找到最长的一段连续的0 长度至少为2才压缩
+        let (mut best_start, mut best_len) = (0, 0);
+        let mut i = 0;
+        while i < 8 {
+            if self.segments[i] == 0 {
+                let start = i;
+                while i < 8 && self.segments[i] == 0 {
+                    i += 1;
+                }
+                if i - start > best_len {
+                    (best_start, best_len) = (start, i - start);
+                }
+            } else {
+                i += 1;
+            }
+        }
+        let hex = |segments: &[u16]| segments.iter().map(|s| format!("{:x}", s)).collect::<Vec<_>>().join(":");
+        let text = if best_len >= 2 {
+            format!("{}::{}", hex(&self.segments[..best_start]), hex(&self.segments[best_start + best_len..]))
+        } else {
+            hex(&self.segments)
+        };
+        f.pad(&text)
+    }
+}
+
+impl fmt::Debug for Ipv6 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl IpAddr {
+    pub fn kind(&self) -> IpAddrKind {
+        match self {
+            IpAddr::V4(_) => IpAddrKind::V4,
+            IpAddr::V6(_) => IpAddrKind::V6,
+        }
+    }
+
+    // 统一转换成u128 方便网段和范围的计算 IPv4只使用低32位
+    pub fn to_bits(self) -> u128 {
+        match self {
+            IpAddr::V4(ip) => ip.to_bits() as u128,
+            IpAddr::V6(ip) => ip.to_bits(),
+        }
+    }
+
+    pub fn from_bits(kind: IpAddrKind, bits: u128) -> IpAddr {
+        match kind {
+            IpAddrKind::V4 => IpAddr::V4(Ipv4::from_bits(bits as u32)),
+            IpAddrKind::V6 => IpAddr::V6(Ipv6::from_bits(bits)),
+        }
+    }
+}
+
+impl IpAddrKind {
+    // 地址的位数
+    pub fn width(self) -> u8 {
+        match self {
+            IpAddrKind::V4 => 32,
+            IpAddrKind::V6 => 128,
+        }
+    }
+}
+
+// 包含:的按IPv6解析 否则按IPv4解析
+impl FromStr for IpAddr {
+    type Err = IpParseError;
+
+    fn from_str(s: &str) -> Result<IpAddr, IpParseError> {
+        if s.contains(':') {
+            Ok(IpAddr::V6(s.parse()?))
+        } else {
+            Ok(IpAddr::V4(s.parse()?))
+        }
+    }
+}
+
+impl fmt::Display for IpAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpAddr::V4(ip) => fmt::Display::fmt(ip, f),
+            IpAddr::V6(ip) => fmt::Display::fmt(ip, f),
+        }
+    }
+}
+
+impl IpInfo {
+    pub fn new(address: IpAddr) -> IpInfo {
+        IpInfo { address }
+    }
+
+    // kind由地址本身决定 不可能出现kind是V4而地址是IPv6的情况
+    pub fn kind(&self) -> IpAddrKind {
+        self.address.kind()
+    }
+}
+
+impl FromStr for IpInfo {
+    type Err = IpParseError;
+
+    fn from_str(s: &str) -> Result<IpInfo, IpParseError> {
+        Ok(IpInfo::new(s.parse()?))
+    }
+}
+
+pub fn demo() {
+    match "127.0.0.1".parse::<IpInfo>() {
+        Ok(info) => println!("{:?} {}", info.kind(), info.address),
+        Err(err) => println!("{}", err),
+    }
+    // 原来的 IpInfo { kind: IpAddrKind::V4, address: String::from("localhost") } 现在无法构造
+    if let Err(err) = "localhost".parse::<IpInfo>() {
+        println!("localhost: {}", err);
+    }
+
+    // 输出的是RFC 5952规定的规范形式 小写 去掉前导0 最长的一串0压缩成::
+    for input in ["2001:0DB8:0000:0000:0000:0000:0000:0001", "1:0:0:2:0:0:0:3", "::FFFF:c000:0201", "1.2.3.256"] {
+        match input.parse::<IpAddr>() {
+            Ok(ip) => println!("{:<42} => {}", input, ip),
+            Err(err) => println!("{:<42} => {}", input, err),
+        }
+    }
+    println!("{} {} {} {}", Ipv4::UNSPECIFIED, Ipv4::LOCALHOST, Ipv6::UNSPECIFIED, Ipv6::LOCALHOST);
+    println!("{:x?}", Ipv6::LOCALHOST.segments());
+    println!("[{:>9}]", Ipv4::new(10, 0, 0, 1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ip_info() {
+        let info: IpInfo = "127.0.0.1".parse().unwrap();
+        assert_eq!(info.kind(), IpAddrKind::V4);
+        assert_eq!(info.address, IpAddr::V4(Ipv4::LOCALHOST));
+        assert_eq!("localhost".parse::<IpInfo>().err(), Some(IpParseError::InvalidChar('l')));
+    }
+
+    // 规范化输出 (输入, RFC 5952形式) 包括::压缩和内嵌的IPv4
+    #[test]
+    fn formats_rfc5952() {
+        let canonical = [
+            ("::1", "::1"),
+            ("::", "::"),
+            ("2001:0DB8:0000:0000:0000:0000:0000:0001", "2001:db8::1"),
+            ("2001:db8:0:0:1:0:0:1", "2001:db8::1:0:0:1"),
+            ("2001:db8:0:1:1:1:1:1", "2001:db8:0:1:1:1:1:1"),
+            ("2001:db8::0:1", "2001:db8::1"),
+            ("fe80::", "fe80::"),
+            ("1:0:0:2:0:0:0:3", "1:0:0:2::3"),
+            ("::ffff:192.0.2.1", "::ffff:192.0.2.1"),
+            ("::FFFF:c000:0201", "::ffff:192.0.2.1"),
+            ("64:ff9b::192.0.2.33", "64:ff9b::c000:221"),
+            ("1:2:3:4:5:6:1.2.3.4", "1:2:3:4:5:6:102:304"),
+            ("1:2:3:4:5:6:7::", "1:2:3:4:5:6:7:0"),
+        ];
+        for (input, expected) in canonical {
+            let ip: IpAddr = input.parse().unwrap();
+            assert_eq!(ip.kind(), IpAddrKind::V6);
+            assert_eq!(ip.to_string(), expected, "{}", input);
+            // 规范形式再解析一次应该得到同一个地址
+            assert_eq!(expected.parse::<IpAddr>(), Ok(ip));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        let invalid = [
+            ("", IpParseError::Empty),
+            ("1.2.3", IpParseError::Ipv4PartCount(3)),
+            ("1.2.3.256", IpParseError::Ipv4Octet(String::from("256"))),
+            ("1.2.3.04", IpParseError::Ipv4Octet(String::from("04"))),
+            ("1..3.4", IpParseError::Ipv4Octet(String::new())),
+            ("1:2:3:4:5:6:7", IpParseError::Ipv6SegmentCount(7)),
+            ("1:2:3:4:5:6:7:8:9", IpParseError::Ipv6SegmentCount(9)),
+            ("1:2:3:4::5:6:7:8", IpParseError::Ipv6SegmentCount(8)),
+            ("1::2::3", IpParseError::DoubleColon),
+            ("12345::", IpParseError::Ipv6Segment(String::from("12345"))),
+            (":1:2:3:4:5:6:7", IpParseError::Ipv6Segment(String::new())),
+            ("1.2.3.4::", IpParseError::EmbeddedIpv4),
+            ("fe80::1%eth0", IpParseError::InvalidChar('%')),
+            ("::ffff:1.2.3", IpParseError::Ipv4PartCount(3)),
+        ];
+        for (input, expected) in invalid {
+            assert_eq!(input.parse::<IpAddr>(), Err(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn constants_and_formatting() {
+        assert_eq!("0.0.0.0".parse(), Ok(Ipv4::UNSPECIFIED));
+        assert_eq!("::".parse(), Ok(Ipv6::UNSPECIFIED));
+        assert_eq!("2001:db8::1".parse::<Ipv6>().map(|ip| ip.segments()), Ok([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1]));
+        assert_eq!(format!("[{:>9}]", Ipv4::new(10, 0, 0, 1)), "[ 10.0.0.1]");
+    }
+}
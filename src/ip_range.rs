@@ -0,0 +1,411 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::atomic_file;
+use crate::cidr::{Cidr, CidrError};
+use crate::{IpAddr, IpAddrKind};
+
+// 地址范围的集合 用来实现防火墙的白名单
+// IPv4和IPv6分开保存 每种都是按起始地址排好序、互不重叠也不相邻的闭区间 [start, end]
+// 插入时和重叠或相邻的区间合并 所以查询只需要一次二分查找 O(log n)
+// 白名单文件每行一个地址或CIDR #之后是注释 空行会被忽略
+
+#[derive(Debug)]
+pub enum RangeSetError {
+    // 起止地址一个是IPv4 一个是IPv6
+    KindMismatch { start: IpAddr, end: IpAddr },
+    // 起始地址大于结束地址
+    Reversed { start: IpAddr, end: IpAddr },
+    // 文件第line行(从1开始)既不是地址也不是CIDR
+    Parse { line: usize, text: String, error: CidrError },
+    Io(io::Error),
+}
+
+impl fmt::Display for RangeSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeSetError::KindMismatch { start, end } => write!(f, "{} 和 {} 不是同一种地址", start, end),
+            RangeSetError::Reversed { start, end } => write!(f, "起始地址 {} 大于结束地址 {}", start, end),
+            RangeSetError::Parse { line, text, error } => write!(f, "第{}行 {:?} 格式错误: {}", line, text, error),
+            RangeSetError::Io(err) => write!(f, "读写文件失败: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RangeSetError {}
+
+impl From<io::Error> for RangeSetError {
+    fn from(err: io::Error) -> RangeSetError {
+        RangeSetError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpRangeSet {
+    v4: Vec<(u128, u128)>,
+    v6: Vec<(u128, u128)>,
+}
+
+impl IpRangeSet {
+    pub fn new() -> IpRangeSet {
+        IpRangeSet::default()
+    }
+
+    fn ranges_of(&self, kind: IpAddrKind) -> &Vec<(u128, u128)> {
+        match kind {
+            IpAddrKind::V4 => &self.v4,
+            IpAddrKind::V6 => &self.v6,
+        }
+    }
+
+    // 把 [start, end] 和所有重叠或相邻的区间合并成一个 再放回原来的位置
+    fn insert_bits(&mut self, kind: IpAddrKind, start: u128, end: u128) {
+        let ranges = match kind {
+            IpAddrKind::V4 => &mut self.v4,
+            IpAddrKind::V6 => &mut self.v6,
+        };
+        // from: 第一个结束地址+1 >= start 的区间 to: 第一个起始地址 > end+1 的区间
+        // 地址等于最大值时+1会溢出 saturating_add 让比较结果仍然正确
+        let from = ranges.partition_point(|&(_, e)| e.saturating_add(1) < start);
+        let to = ranges.partition_point(|&(s, _)| s <= end.saturating_add(1));
+        let (mut start, mut end) = (start, end);
+        if from < to {
+            start = start.min(ranges[from].0);
+            end = end.max(ranges[to - 1].1);
+        }
+        ranges.splice(from..to, [(start, end)]);
+    }
+
+    pub fn insert(&mut self, ip: IpAddr) {
+        self.insert_bits(ip.kind(), ip.to_bits(), ip.to_bits());
+    }
+
+    pub fn insert_cidr(&mut self, cidr: Cidr) {
+        self.insert_bits(cidr.kind(), cidr.network().to_bits(), cidr.last().to_bits());
+    }
+
+    pub fn insert_range(&mut self, start: IpAddr, end: IpAddr) -> Result<(), RangeSetError> {
+        if start.kind() != end.kind() {
+            return Err(RangeSetError::KindMismatch { start, end });
+        }
+        if start.to_bits() > end.to_bits() {
+            return Err(RangeSetError::Reversed { start, end });
+        }
+        self.insert_bits(start.kind(), start.to_bits(), end.to_bits());
+        Ok(())
+    }
+
+    // 找到第一个结束地址 >= ip 的区间 ip在集合中当且仅当它也不小于这个区间的起始地址
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let bits = ip.to_bits();
+        let ranges = self.ranges_of(ip.kind());
+        let i = ranges.partition_point(|&(_, end)| end < bits);
+        ranges.get(i).is_some_and(|&(start, _)| start <= bits)
+    }
+
+    // 合并后的区间个数
+    pub fn len(&self) -> usize {
+        self.v4.len() + self.v6.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.v4.is_empty() && self.v6.is_empty()
+    }
+
+    // 某种地址的总数 整个IPv6空间有2^128个地址 超过u128时返回u128::MAX
+    pub fn address_count(&self, kind: IpAddrKind) -> u128 {
+        self.ranges_of(kind).iter().fold(0u128, |sum, &(start, end)| sum.saturating_add(end - start).saturating_add(1))
+    }
+
+    // 先IPv4后IPv6 按地址从小到大
+    pub fn ranges(&self) -> impl Iterator<Item = (IpAddr, IpAddr)> + '_ {
+        let v4 = self.v4.iter().map(|&(s, e)| (IpAddr::from_bits(IpAddrKind::V4, s), IpAddr::from_bits(IpAddrKind::V4, e)));
+        let v6 = self.v6.iter().map(|&(s, e)| (IpAddr::from_bits(IpAddrKind::V6, s), IpAddr::from_bits(IpAddrKind::V6, e)));
+        v4.chain(v6)
+    }
+
+    // 用最少的CIDR表示整个集合 例如 10.0.0.1 - 10.0.0.6 => .1/32 .2/31 .4/31 .6/32
+    pub fn to_cidrs(&self) -> Vec<Cidr> {
+        let mut out = Vec::new();
+        for kind in [IpAddrKind::V4, IpAddrKind::V6] {
+            for &(start, end) in self.ranges_of(kind) {
+                range_to_cidrs(kind, start, end, &mut out);
+            }
+        }
+        out
+    }
+
+    // 解析白名单文本 每行一个地址或CIDR 单个地址相当于 /32 或 /128
+    pub fn parse(text: &str) -> Result<IpRangeSet, RangeSetError> {
+        let mut set = IpRangeSet::new();
+        for (i, line) in text.lines().enumerate() {
+            let entry = line.split('#').next().unwrap_or("").trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let parse_error = |error| RangeSetError::Parse { line: i + 1, text: entry.to_string(), error };
+            if entry.contains('/') {
+                set.insert_cidr(entry.parse().map_err(parse_error)?);
+            } else {
+                let ip: IpAddr = entry.parse().map_err(|err| parse_error(CidrError::Address(err)))?;
+                set.insert(ip);
+            }
+        }
+        Ok(set)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<IpRangeSet, RangeSetError> {
+        IpRangeSet::parse(&fs::read_to_string(path)?)
+    }
+
+    // 写成最少的CIDR 单个地址不带前缀 用 atomic_file::write 先写临时文件再rename
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RangeSetError> {
+        let path = path.as_ref();
+        let mut content = format!("# {}个区间 由 myrust 生成\n", self.len());
+        for cidr in self.to_cidrs() {
+            if cidr.prefix() == cidr.kind().width() {
+                content.push_str(&format!("{}\n", cidr.network()));
+            } else {
+                content.push_str(&format!("{}\n", cidr));
+            }
+        }
+        atomic_file::write(path, content.as_bytes())?;
+        Ok(())
+    }
+}
+
+// 低bits位全为1
+fn ones(bits: u32) -> u128 {
+    if bits == 0 {
+        0
+    } else {
+        u128::MAX >> (128 - bits)
+    }
+}
+
+// 每次从start开始取按start对齐、又不超过end的最大网段
+fn range_to_cidrs(kind: IpAddrKind, start: u128, end: u128, out: &mut Vec<Cidr>) {
+    let width = kind.width() as u32;
+    let mut start = start;
+    loop {
+        let mut bits = start.trailing_zeros().min(width);
+        while bits > 0 && ones(bits) > end - start {
+            bits -= 1;
+        }
+        if let Ok(cidr) = Cidr::new(IpAddr::from_bits(kind, start), (width - bits) as u8) {
+            out.push(cidr);
+        }
+        match (start | ones(bits)).checked_add(1) {
+            Some(next) if next <= end => start = next,
+            _ => break,
+        }
+    }
+}
+
+// myrust check-ip allow.txt 10.1.2.3 ... 逐个检查地址是否在白名单中 有地址被拒绝或出错时返回false
+pub fn check_file(path: &str, ips: &[String]) -> bool {
+    let set = match IpRangeSet::load(path) {
+        Ok(set) => set,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            return false;
+        }
+    };
+    let mut ok = true;
+    for text in ips {
+        match text.parse::<IpAddr>() {
+            Ok(ip) if set.contains(&ip) => println!("{} 允许", ip),
+            Ok(ip) => {
+                println!("{} 拒绝", ip);
+                ok = false;
+            }
+            Err(err) => {
+                eprintln!("{}: {}", text, err);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+fn print_set(set: &IpRangeSet) {
+    for (start, end) in set.ranges() {
+        println!("  {} - {}", start, end);
+    }
+    println!("  IPv4地址{}个 IPv6地址{}个", set.address_count(IpAddrKind::V4), set.address_count(IpAddrKind::V6));
+}
+
+pub fn demo() {
+    let mut set = IpRangeSet::new();
+    println!("新建的集合为空: {}", set.is_empty());
+    for cidr in ["10.0.0.0/24", "10.0.2.0/24", "2001:db8::/32", "10.0.1.0/24"] {
+        match cidr.parse() {
+            Ok(cidr) => set.insert_cidr(cidr),
+            Err(err) => println!("{}: {}", cidr, err),
+        }
+    }
+    // 10.0.1.0/24 把前后两段连起来 相邻的单个地址也会合并
+    for ip in ["192.168.1.10", "192.168.1.11"] {
+        if let Ok(ip) = ip.parse() {
+            set.insert(ip);
+        }
+    }
+    println!("合并后有{}个区间", set.len());
+    print_set(&set);
+
+    for addr in ["10.0.1.200", "10.0.3.0", "192.168.1.12", "2001:db8:1::1", "::ffff:10.0.0.1"] {
+        if let Ok(ip) = addr.parse() {
+            println!("{} 在白名单中: {}", addr, set.contains(&ip));
+        }
+    }
+
+    for (start, end) in [("10.0.0.9", "10.0.0.1"), ("10.0.0.9", "::1"), ("172.16.0.1", "172.16.0.6")] {
+        if let (Ok(start), Ok(end)) = (start.parse(), end.parse()) {
+            if let Err(err) = set.insert_range(start, end) {
+                println!("{}", err);
+            }
+        }
+    }
+    let cidrs: Vec<String> = set.to_cidrs().iter().map(|c| c.to_string()).collect();
+    println!("写成CIDR: {}", cidrs.join(", "));
+
+    // 保存到临时目录再检查 目录不可写时只打印错误
+    let path = std::env::temp_dir().join(format!("myrust_allow_{}.txt", std::process::id()));
+    match set.save(&path) {
+        Ok(()) => {
+            if let Ok(saved) = fs::read_to_string(&path) {
+                println!("{}", saved);
+            }
+            check_file(&path.to_string_lossy(), &[String::from("10.0.1.1"), String::from("2001:db9::")]);
+            let _ = fs::remove_file(&path);
+        }
+        Err(err) => println!("{}", err),
+    }
+
+    // 出错时报告行号
+    if let Err(err) = IpRangeSet::parse("10.0.0.0/8\n\n10.0.0.256\n") {
+        println!("{}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn sample() -> IpRangeSet {
+        let mut set = IpRangeSet::new();
+        set.insert_cidr("10.0.0.0/24".parse().unwrap());
+        set.insert_cidr("10.0.2.0/24".parse().unwrap());
+        set.insert(ip("192.168.1.10"));
+        set.insert_cidr("2001:db8::/32".parse().unwrap());
+        // 10.0.1.0/24 把前后两段连起来 三段合并成一个区间
+        set.insert_cidr("10.0.1.0/24".parse().unwrap());
+        // 相邻的单个地址也会合并
+        set.insert(ip("192.168.1.11"));
+        set
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent() {
+        let mut set = IpRangeSet::new();
+        assert!(set.is_empty());
+        set.insert_cidr("10.0.0.0/24".parse().unwrap());
+        set.insert_cidr("10.0.2.0/24".parse().unwrap());
+        set.insert(ip("192.168.1.10"));
+        set.insert_cidr("2001:db8::/32".parse().unwrap());
+        assert_eq!(set.len(), 4);
+        assert_eq!(sample().len(), 3);
+        let ranges: Vec<String> = sample().ranges().map(|(s, e)| format!("{} - {}", s, e)).collect();
+        assert_eq!(
+            ranges,
+            ["10.0.0.0 - 10.0.2.255", "192.168.1.10 - 192.168.1.11", "2001:db8:: - 2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"]
+        );
+        assert_eq!(sample().address_count(IpAddrKind::V4), 768 + 2);
+        assert_eq!(sample().address_count(IpAddrKind::V6), 1 << 96);
+    }
+
+    #[test]
+    fn looks_up_addresses() {
+        let set = sample();
+        for (addr, expected) in [
+            ("10.0.1.200", true),
+            ("10.0.3.0", false),
+            ("9.255.255.255", false),
+            ("192.168.1.11", true),
+            ("192.168.1.12", false),
+            ("2001:db8:1::1", true),
+            ("2001:db9::", false),
+            // IPv4映射的IPv6地址和IPv4地址不是同一个地址
+            ("::ffff:10.0.0.1", false),
+        ] {
+            assert_eq!(set.contains(&ip(addr)), expected, "{}", addr);
+        }
+    }
+
+    #[test]
+    fn ranges_to_minimal_cidrs() {
+        let mut set = sample();
+        assert!(matches!(set.insert_range(ip("10.0.0.9"), ip("::1")), Err(RangeSetError::KindMismatch { .. })));
+        assert!(matches!(set.insert_range(ip("10.0.0.9"), ip("10.0.0.1")), Err(RangeSetError::Reversed { .. })));
+        set.insert_range(ip("172.16.0.1"), ip("172.16.0.6")).unwrap();
+        let cidrs: Vec<String> = set.to_cidrs().iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            cidrs,
+            [
+                "10.0.0.0/23",
+                "10.0.2.0/24",
+                "172.16.0.1/32",
+                "172.16.0.2/31",
+                "172.16.0.4/31",
+                "172.16.0.6/32",
+                "192.168.1.10/31",
+                "2001:db8::/32"
+            ]
+        );
+    }
+
+    // 包含最大地址的区间不会溢出
+    #[test]
+    fn whole_address_space() {
+        let mut all = IpRangeSet::new();
+        all.insert_cidr("0.0.0.0/0".parse().unwrap());
+        all.insert_cidr("::/0".parse().unwrap());
+        all.insert(ip("255.255.255.255"));
+        assert_eq!(all.len(), 2);
+        assert!(all.contains(&ip("255.255.255.255")) && all.contains(&ip("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff")));
+        assert_eq!(all.address_count(IpAddrKind::V6), u128::MAX);
+        assert_eq!(all.to_cidrs().iter().map(|c| c.to_string()).collect::<Vec<_>>(), ["0.0.0.0/0", "::/0"]);
+    }
+
+    // 白名单文件 保存后再读回来应该得到同一个集合
+    #[test]
+    fn file_round_trip() {
+        let mut set = sample();
+        set.insert_range(ip("172.16.0.1"), ip("172.16.0.6")).unwrap();
+        let text = "# 办公网\n10.0.0.0/23\n10.0.2.0/24   # 机房\n\n192.168.1.10\n192.168.1.11\n172.16.0.1\n172.16.0.2/31\n172.16.0.4/31\n172.16.0.6\n2001:db8::/32\n";
+        assert_eq!(IpRangeSet::parse(text).unwrap(), set);
+
+        let path = std::env::temp_dir().join(format!("myrust_allow_test_{}.txt", std::process::id()));
+        set.save(&path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.lines().any(|line| line == "172.16.0.1"));
+        assert_eq!(IpRangeSet::load(&path).unwrap(), set);
+        assert!(check_file(path.to_str().unwrap(), &[String::from("10.0.1.1"), String::from("2001:db8::8")]));
+        assert!(!check_file(path.to_str().unwrap(), &[String::from("10.0.3.1")]));
+        let _ = fs::remove_file(&path);
+        assert!(matches!(IpRangeSet::load(&path), Err(RangeSetError::Io(_))));
+    }
+
+    // 出错时报告行号
+    #[test]
+    fn reports_line_numbers() {
+        assert!(matches!(IpRangeSet::parse("10.0.0.0/8\n\n10.0.0.256\n"), Err(RangeSetError::Parse { line: 3, .. })));
+        assert!(matches!(IpRangeSet::parse("10.0.0.0/33"), Err(RangeSetError::Parse { line: 1, .. })));
+    }
+}
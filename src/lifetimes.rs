@@ -0,0 +1,211 @@
+use std::borrow::Cow;
+use std::thread;
+
+use crate::User;
+
+// 生命周期 标注的作用是告诉编译器 返回的引用和哪个参数的引用活得一样久
+// 生命周期标注不会改变任何值的存活时间 只是描述多个引用之间的关系 让借用检查器能够验证
+// main.rs 中被注释掉的 dangle 返回了一个局部变量的引用 下面给出几种能通过编译的替代写法
+
+// 返回值可能来自x也可能来自y 编译器无法自己推断 需要标注'a
+// 'a 的实际长度是x和y中较短的那个
+pub fn longest<'a>(x: &'a str, y: &'a str) -> &'a str {
+    if x.chars().count() >= y.chars().count() {
+        x
+    } else {
+        y
+    }
+}
+
+// 返回值只和x有关时 y不需要和x有相同的生命周期
+pub fn first_of<'a>(x: &'a str, _y: &str) -> &'a str {
+    x
+}
+
+// 生命周期省略规则 满足以下规则时可以不写标注
+// 1.每个引用参数都会获得独立的生命周期
+// 2.只有一个引用参数时 它的生命周期被赋给所有返回的引用
+// 3.方法有 &self 或 &mut self 时 self的生命周期被赋给所有返回的引用
+// 下面的函数按规则2展开后等价于 fn trim_greeting<'a>(s: &'a str) -> &'a str
+pub fn trim_greeting(s: &str) -> &str {
+    s.strip_prefix("你好 ").unwrap_or(s)
+}
+
+// 结构体持有引用时必须标注生命周期 表示 UserView 不能比它借用的 User 活得更久
+pub struct UserView<'a> {
+    pub username: &'a str,
+    pub email: &'a str,
+}
+
+impl<'a> UserView<'a> {
+    pub fn new(user: &'a User) -> UserView<'a> {
+        UserView { username: &user.username, email: &user.email }
+    }
+
+    // 按规则3 不写标注时返回值的生命周期会和&self绑定
+    // 这里显式写成'a 表示返回的切片借用的是User 即使UserView本身被drop了也仍然有效
+    pub fn domain(&self) -> &'a str {
+        self.email.split_once('@').map(|(_, domain)| domain).unwrap_or_default()
+    }
+
+    // 这里用的就是规则3 返回值和&self绑定
+    pub fn display_name(&self) -> &str {
+        if self.username.is_empty() {
+            self.email
+        } else {
+            self.username
+        }
+    }
+}
+
+// 'static 表示引用在整个程序运行期间都有效 字符串字面量被编译进二进制文件 所以都是 &'static str
+pub const GREETING: &str = "hello";
+
+pub fn greeting() -> &'static str {
+    GREETING
+}
+
+// T: 'static 的含义是T不包含任何非'static的引用 而不是T永远不会被释放
+// thread::spawn 要求闭包是'static的 因为新线程可能比当前函数活得更久
+pub fn spawn_len<T: AsRef<str> + Send + 'static>(value: T) -> usize {
+    thread::spawn(move || value.as_ref().chars().count()).join().unwrap_or_default()
+}
+
+// ========= dangle 的几种修正方式 =========
+
+// 1.直接返回拥有所有权的String 所有权被移动给调用方 最常用的写法
+pub fn no_dangle() -> String {
+    String::from("hello")
+}
+
+// 2.内容是固定的 返回 &'static str 不需要分配内存
+pub fn no_dangle_static() -> &'static str {
+    "hello"
+}
+
+// 3.由调用方提供存放数据的缓冲区 返回的引用借用的是调用方的数据
+pub fn no_dangle_into(buffer: &mut String) -> &str {
+    buffer.clear();
+    buffer.push_str("hello");
+    buffer
+}
+
+// 4.有时返回借用 有时返回新分配的值 用Cow(clone on write)把两种情况统一成一个类型
+pub fn no_dangle_cow(name: &str) -> Cow<'_, str> {
+    if name.is_empty() {
+        Cow::Owned(String::from("hello"))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+// longest 的拥有所有权版本 结果不受参数生命周期的限制
+pub fn longest_owned(x: &str, y: &str) -> String {
+    longest(x, y).to_string()
+}
+
+pub fn demo() {
+    let string1 = String::from("long string is long");
+    let result;
+    {
+        let string2 = String::from("xyz");
+        println!("longest = {}", longest(string1.as_str(), string2.as_str()));
+        // 如果把结果保存到外层的result 再在这里结束string2的作用域 编译器会报错 E0597
+        // 此时改用拥有所有权的版本
+        result = longest_owned(string1.as_str(), string2.as_str());
+        println!("first_of = {}", first_of(&string2, &string1));
+    }
+    println!("the longest string is {}", result);
+    // 按字符数而不是字节数比较
+    println!("longest(\"你好\", \"abc\") = {}", longest("你好", "abc"));
+    println!("trim_greeting = {}", trim_greeting("你好 世界"));
+
+    let user = User {
+        username: String::from("张三"),
+        email: String::from("zhangsan@example.com"),
+        sign_in_count: 1,
+        active: true,
+    };
+    let domain;
+    {
+        let view = UserView::new(&user);
+        println!("display_name = {}", view.display_name());
+        domain = view.domain();
+    }
+    // view已经被drop 但domain借用的是user 所以仍然可以使用
+    println!("domain = {}", domain);
+    let anonymous = User { username: String::new(), ..user };
+    println!("匿名用户的display_name = {}", UserView::new(&anonymous).display_name());
+
+    // String 不包含引用 满足'static约束 &'static str 也满足
+    println!("{} spawn_len = {} {}", greeting(), spawn_len(String::from("你好")), spawn_len(GREETING));
+
+    let mut buffer = String::new();
+    println!(
+        "{} {} {} {} {}",
+        no_dangle(),
+        no_dangle_static(),
+        no_dangle_into(&mut buffer),
+        no_dangle_cow(""),
+        no_dangle_cow("world")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 替代写法1: 返回拥有所有权的String 结果可以比参数活得更久
+    #[test]
+    fn returns_owned_string() {
+        let result;
+        {
+            let string2 = String::from("xyz");
+            result = longest_owned("long string is long", &string2);
+        }
+        assert_eq!(result, "long string is long");
+        assert_eq!(no_dangle(), "hello");
+        assert!(matches!(no_dangle_cow(""), Cow::Owned(_)));
+    }
+
+    // 替代写法2: 返回的引用借用自参数 和参数的生命周期'a绑定
+    #[test]
+    fn borrows_from_argument() {
+        let string1 = String::from("long string is long");
+        let string2 = String::from("xyz");
+        assert_eq!(longest(&string1, &string2), "long string is long");
+        assert_eq!(first_of(&string2, &string1), "xyz");
+        // 按字符数而不是字节数比较
+        assert_eq!(longest("你好", "abc"), "abc");
+        assert_eq!(trim_greeting("你好 世界"), "世界");
+        let mut buffer = String::new();
+        assert_eq!(no_dangle_into(&mut buffer), "hello");
+        assert!(matches!(no_dangle_cow("world"), Cow::Borrowed("world")));
+
+        let user = User {
+            username: String::from("张三"),
+            email: String::from("zhangsan@example.com"),
+            sign_in_count: 1,
+            active: true,
+        };
+        let domain;
+        {
+            let view = UserView::new(&user);
+            assert_eq!(view.display_name(), "张三");
+            domain = view.domain();
+        }
+        assert_eq!(domain, "example.com");
+        let anonymous = User { username: String::new(), ..user };
+        assert_eq!(UserView::new(&anonymous).display_name(), "zhangsan@example.com");
+    }
+
+    // 替代写法3: 返回 &'static str 满足任何生命周期要求 包括线程的'static约束
+    #[test]
+    fn returns_static_str() {
+        let s: &'static str = no_dangle_static();
+        assert_eq!(s, "hello");
+        assert_eq!(greeting(), "hello");
+        assert_eq!(spawn_len(GREETING), 5);
+        assert_eq!(spawn_len(String::from("你好")), 2);
+    }
+}
@@ -3,9 +3,48 @@ use std::iter::Iterator;
 use std::ops::Index;
 use std::ops::Add;
 use num::complex::Complex;
+use describe_derive::Describe;
+use crate::ip::{Ipv4, Ipv6};
+use crate::template::{print_line, Value};
 use crate::IpAddrKind::V4;
 
+mod atomic_file;
+mod cidr;
+mod codec;
+mod describe;
+mod drop_order;
+mod escape;
+mod gap_buffer;
+mod history;
+mod ip;
+mod ip_range;
+mod lifetimes;
+mod message_bus;
+mod naming;
+#[cfg(test)]
+mod rng;
+mod safe_str;
+mod script;
+mod smart_pointers;
+mod template;
+mod tokenizer;
+mod traced;
+mod tracked;
+mod turtle;
+mod unicode;
+mod user_builder;
+mod user_store;
+mod user_traits;
+mod worker_pool;
+
 fn main() {
+    //带参数运行时执行对应的子命令 例如 cargo run -- bench-text 不带参数时按顺序运行下面所有的课程
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        run_command(&args);
+        return;
+    }
+
     //++++++++++++++++++++++++++++++++++++++++++++++++++++变量绑定与解构++++++++++++++++++++++++++++++++++++++++++++++++++++++
     let a = "hello world!";
     println!("{}", a);
@@ -189,6 +228,8 @@ fn main() {
     println!("c = {}, emoji = {}, japanese = {}", c, emoji, japanese);
     //rust中的字符类型是32位的，可以存储任意的unicode字符
     println!("字符'张'占用了{}个字节", std::mem::size_of_val(&c));
+    //但屏幕上的一个"字"不一定是一个char 带肤色的emoji、组合重音符号都由多个char组成 见 unicode/mod.rs
+    unicode::demo();
 
     //====================布尔类型====================
     let _b = true;
@@ -228,6 +269,8 @@ fn main() {
     }
     let f = plus_five(1);
     println!("f = {}", f);
+    //命名风格的转换和检查见 naming.rs
+    naming::demo();
 
     //发散函数 diverging function 一般用于panic 用!作函数返回类型的时候，表示这个函数永远不会返回
     fn _diverges() -> ! {
@@ -304,6 +347,8 @@ fn main() {
     let s2 = String::from("hello"); // s2 进入作用域
     let s3 = takes_and_gives_back(s2); // s2 被移动到 takes_and_gives_back 中,它也将返回值移动给 s3
     println!("s1 = {}, s3 = {}", s1, s3); //这里如果尝试打印s2会报错，因为s2已经失效了
+    //上面的所有权转移只能靠注释描述 traced.rs 用一个会打印日志的包装类型把移动、复制和drop的时间线打印出来
+    traced::demo();
 
     //=====引用和借用=======
 
@@ -347,12 +392,16 @@ fn main() {
     //let r3 = &mut s; //可变引用
     //println!("{}, {}, and {}", r1, r2, r3); //这里会报错，因为r1和r2是不可变引用，r3是可变引用，可变引用和不可变引用不能同时存在
     assert_eq!(r1, r2); //这里可以使用断言，因为r1和r2是不可变引用，所以不会发生数据竞争
+    //上面这些借用冲突在编译期就会报错 没法运行 tracked.rs 在运行时模拟同样的规则并打印出E0499/E0502错误
+    tracked::demo();
 
     //NOTICE!!! 引用的作用域是从声明开始一直持续到最后一次使用为止 和变量有所不同 变量的作用域是从声明开始一直持续到当前作用域结束为止(即到达'}'为止)
 
     //悬垂引用
     //悬垂引用是指指向了已经被释放的内存的指针，rust编译器会阻止这种情况的发生 即在引用结束前不允许变量被释放
     //let reference_to_nothing = dangle(); //这里会报错，因为dangle函数返回的是一个指向堆上数据的引用，但是dangle函数结束后，这个数据就被释放了，所以这里会报错
+    //生命周期标注 以及dangle的几种正确写法 见 lifetimes.rs
+    lifetimes::demo();
 
     //==================================================================复合类型==================================================================
     //=======================字符串与切片=======================
@@ -363,19 +412,20 @@ fn main() {
     //字符串切片
     let hello = &s[0..5]; //btw 这种写法叫序列 详见 main.rs: 154
     let world = &s[6..11];
-    println!("hello = {}, world = {}", hello, world);
+    print_line("hello = {hello}, world = {world}", &[("hello", Value::from(hello)), ("world", Value::from(world))]);
     //类似golang切片操作 从索引0开始截取和截取到最后一个元素的写法分别如下
     let hello = &s[..5];
     let world = &s[6..];
-    println!("hello = {}, world = {}", hello, world);
+    print_line("hello = {hello}, world = {world}", &[("hello", Value::from(hello)), ("world", Value::from(world))]);
     //NOTICE 对于字符串使用切片语法时 切片的索引必须是有效的字符索引 如果使用无效的字符索引会导致panic 比如汉字在UTF-8编码中占3个字节 截取&s[2..]会panic
     let _s = "你好世界";
     //let cut = &s[2..]; //这句报错：panicked at 'byte index 2 is not a char boundary; it is inside '你' (bytes 0..3) of `你好世界`'
     //println!("cut = {}",cut);
     let mut s = String::from("hello world");
-    let word = first_word(&s);
-    println!("word = {}", word); //这句打开 下面一句就不会报错 因为不可变借用已经使用过了
+    let word = tokenizer::first_word(&s); //first_word返回的是对s的借用 见 tokenizer.rs
+    print_line("word = {word}", &[("word", Value::from(word))]); //这句打开 下面一句就不会报错 因为不可变借用已经使用过了
     //s.clear(); //这句的参数是对自身的可变借用 然而word是对s的不可变借用 所以这里会报错 参见main.rs: 337
+    tokenizer::demo();
 
     //=========其它切片========
     let a = [1, 2, 3, 4, 5];
@@ -392,7 +442,7 @@ fn main() {
     let s = &s[..]; //转换为字符串切片
     let s = s.to_string(); //转换为字符串对象
     let s = s.as_str(); //转换为字符串切片
-    println!("s = {}", s);
+    print_line("s = {s}", &[("s", Value::from(s))]);
     //字符串索引
     let s = String::from("hello");
     //let h = s[0]; //这里会报错 `String` cannot be indexed by `{integer}`
@@ -405,56 +455,62 @@ fn main() {
         又不允许遍历到合法字符的结尾，所以干脆禁止这样做
     */
     let h = s.index(0..1);
-    println!("h = {}", h);
+    print_line("h = {h}", &[("h", Value::from(h))]);
 
     //字符串操作
     let mut s = String::from("hello"); //可操作的字符串必须是可变的
     //追加
     s.push_str(" world"); //追加字符串
     s.push('!');//追加字符
-    println!("s = {}", s);
+    print_line("s = {s}", &[("s", Value::from(&s))]);
     //插入
     s.insert_str(0, "hello Rust"); //在索引0处插入字符串
     s.insert(10, '!'); //在索引10处插入字符
-    println!("s = {}", s);
+    print_line("s = {s}", &[("s", Value::from(&s))]);
     //替换
     let mut s1 = s.replace("rust", "RUST");//适用于String和&str 返回的是一个新字符串 原字符串不必mut
     let mut s2 = s1.replacen("RUST", "rust", 1); //适用于String和&str 返回的是一个新字符串 原字符串不必mut 只替换第一个目标值
     s2.replace_range(0..5, "HELLO"); //适用于String 直接操作原字符串 必须mut
-    println!("s = {:?}", s2);
+    print_line("s = {s:?}", &[("s", Value::from(&s2))]);
+    //String底层是连续的Vec<u8> 在开头插入需要移动后面所有字节 另一种适合频繁编辑的结构见 gap_buffer.rs
+    gap_buffer::demo();
     //删除
     let p1 = s.pop();//删除最后一个字符并返回
     println!("p1 = {:?}", p1);
     let mut s = String::from("你好你好");
     s.remove(0);//删除第一个字符 // remove的参数如果不是合法字符的边界会报错
-    println!("s = {}", s);
+    print_line("s = {s}", &[("s", Value::from(&s))]);
     //s.remove(2); //panic 索引2是非法边界
     s.truncate(3);//删除索引3之后的所有字符
-    println!("s = {}", s);
+    print_line("s = {s}", &[("s", Value::from(&s))]);
     s.clear();//清空字符串
     dbg!(s);
+    //上面的remove和truncate使用的都是字节索引 传错就会panic 按字符下标操作的安全版本见 safe_str.rs
+    safe_str::demo();
     //连接
     let s1 = String::from("hello ");
     let s2 = String::from("world");
     let s3 = s1 + &s2; //s2会自动解引用为&str类型 s1的所有权被转移 不能再打印s1 ‘+’是add() s1的所有权被转移到add()里了
     let mut s3 = s3 + "!";
     s3 += "!";
-    println!("s3 = {}", s3);
+    print_line("s3 = {s3}", &[("s3", Value::from(&s3))]);
     // !format方式
     let s1 = String::from("hello ");
     let s2 = String::from("world");
     let s3 = format!("{}{}{}", s1, s2, "!"); //format!宏会返回一个String对象
-    println!("s3 = {}", s3);
+    print_line("s3 = {s3}", &[("s3", Value::from(&s3))]);
+    //format!的格式串必须是编译期确定的字面量 运行时的模板见 template.rs
+    template::demo();
     //字符串转义：可以通过'\'输出 ASCII 和 Unicode 字符
     let s = "hello world \x52\x75\x73\x74\n";
-    println!("s = {}", s);
+    print_line("s = {s}", &[("s", Value::from(s))]);
     // \u 可以输出一个 unicode 字符
     let unicode_codepoint = "\u{211D}";
     let character_name = "\"DOUBLE-STRUCK CAPITAL R\"";
 
-    println!(
-        "Unicode character {} (U+211D) is called {}",
-        unicode_codepoint, character_name
+    print_line(
+        "Unicode character {char} (U+211D) is called {name}",
+        &[("char", Value::from(unicode_codepoint)), ("name", Value::from(character_name))],
     );
 
     // 换行了也会保持之前的字符串格式
@@ -477,17 +533,19 @@ fn main() {
     // 如果还是有歧义，可以继续增加，没有限制
     let longer_delimiter = r###"A string with "# in it. And even "##!"###;
     println!("{}", longer_delimiter);
+    //解析和生成以上各种字面量的完整规则见 escape.rs
+    escape::demo();
 
     //=======================================元组=======================================
     let tup: (i32, f64, u8) = (500, 6.4, 1);
     //可以用模式匹配或者.索引的方式获取元组的值
     let (x, y, z) = tup;
-    println!("x = {}, y = {}, z = {}", x, y, z);
-    println!("tup.0 = {}, tup.1 = {}, tup.2 = {}", tup.0, tup.1, tup.2);
+    print_line("x = {x}, y = {y}, z = {z}", &[("x", Value::from(x)), ("y", Value::from(y)), ("z", Value::from(z))]);
+    print_line("tup.0 = {}, tup.1 = {}, tup.2 = {}", &[("0", Value::from(tup.0)), ("1", Value::from(tup.1)), ("2", Value::from(tup.2))]);
     //元组可以作为函数的参数和返回值
     let s1 = String::from("hello");
     let (s2, len) = calculate_length2(s1);
-    println!("s2 = {}, len = {}", s2, len);
+    print_line("s2 = {s2}, len = {len}", &[("s2", Value::from(&s2)), ("len", Value::from(len))]);
 
     //=======================================结构体=======================================
     /*
@@ -506,13 +564,17 @@ fn main() {
     println!("u1.username = {}", u1.username);
     let u2 = build_user(String::from("aaa@example.com"), String::from("王五"));
     println!("u2.username = {}", u2.username);
-    // 利用已有的user1 创建user3
-    let u3 = User {
-        email: String::from("xxx@example.com"),
-        ..u1
-    }; // u3和u1只有email字段不同 所以可以用 ..u1 语法来创建u3 ..u1这种写法必须在结构体尾部使用
-    //username字段发生了所有权转移
-    println!("u3.username = {}", u3.username);
+    //保存一组用户 检查用户名和邮箱唯一 并持久化到文件 见 user_store.rs
+    user_store::demo();
+    // 利用已有的user1 创建user3 u3和u1只有email字段不同
+    // 可以用结构体更新语法 User { email: String::from("lisi@example.com"), ..u1 } 创建u3 ..u1这种写法必须在结构体尾部使用
+    // 但这样u1的username字段会发生所有权转移 之后u1.username就不能再使用了
+    // with_email 会校验新邮箱 并复制其余字段 u1仍然完整可用 见 user_builder.rs
+    let u3 = u1.with_email("lisi@example.com").unwrap();
+    println!("u3.username = {}, u1.email = {}", u3.username, u1.email);
+    user_builder::demo();
+    //变量遮蔽、借用作用域、..u1的部分移动 这些值分别在什么时候被drop 见 drop_order.rs
+    drop_order::demo();
 
     //元组结构体 结构体需要名称，但是结构体字段不一定需要
     struct Color(i32, i32, i32);
@@ -533,11 +595,14 @@ fn main() {
     let _six = IpAddrKind::V6;
     print_IpAddrKind(&four);
 
-    let info = IpInfo {
-        kind: IpAddrKind::V4,
-        address: String::from("127.0.0.1"),
-    };
+    //IpInfo的kind由地址决定 不需要也不能手动指定 地址的解析和格式化 见 ip.rs
+    let info = IpInfo::new(IpAddr::V4(Ipv4::new(127, 0, 0, 1)));
     print_IpInfo(info);
+    ip::demo();
+    //基于IpAddr的子网计算 也可以运行 cargo run -- cidr 192.168.1.0/24 见 cidr.rs
+    cidr::demo();
+    //多个网段合并成地址范围集合 用作白名单 也可以运行 cargo run -- check-ip allow.txt 10.0.0.1 见 ip_range.rs
+    ip_range::demo();
     // 枚举成员可以包含数据 见PokerCard
     let clubs = PokerCard::Clubs(1);
     let spades = PokerCard::Spades(2);
@@ -556,6 +621,12 @@ fn main() {
     send_Msg(m2);
     send_Msg(m3);
     send_Msg(m4);
+    //每个成员带一个tag 接收方match一下就能还原出具体的消息 枚举很适合做二进制协议 见 codec.rs
+    codec::demo();
+    //按消息类型把Message转发给订阅它的处理函数 上面说的"接收并转发Message"的函数 见 message_bus.rs
+    message_bus::demo();
+    //生产者线程通过mpsc通道发送Message 由固定数量的工作线程处理 收到Quit后优雅关闭 见 worker_pool.rs
+    worker_pool::demo();
     //Option枚举 用于处理空值
     /*
         enum Option<T> {
@@ -653,14 +724,8 @@ fn main() {
     //match本身是一个表达式，它的返回值是每个分支的返回值的公共类型
     let ip = IpAddrKind::V4;
     let ip_info = match ip {
-        IpAddrKind::V4 => IpInfo {
-            kind: IpAddrKind::V4,
-            address: String::from("localhost"),
-        },
-        IpAddrKind::V6 => IpInfo {
-            kind: IpAddrKind::V6,
-            address: String::from("::1"),
-        }
+        IpAddrKind::V4 => IpInfo::new(IpAddr::V4(Ipv4::LOCALHOST)),
+        IpAddrKind::V6 => IpInfo::new(IpAddr::V6(Ipv6::LOCALHOST)),
     };
     print_IpInfo(ip_info);
 
@@ -688,6 +753,19 @@ fn main() {
     if let Action::Say(s) = &actions[0] {
         println!("if let say {}", s);
     }
+    //不只是打印 用同样的match把actions真正执行在一块画布上 见 turtle.rs
+    let mut canvas = turtle::Canvas::new(4, 3);
+    canvas.run(&actions).unwrap();
+    print!("{}", canvas.to_ascii());
+    turtle::demo();
+    //同样的actions也可以写成脚本 从文件读取 不用在代码里写死 见 script.rs
+    match script::parse_actions("say \"hello\"\nmove 1 2\ncolor 255 255 0\n") {
+        Ok(parsed) => println!("parsed = {:?}", parsed),
+        Err(err) => println!("{}", err),
+    }
+    script::demo();
+    //每个动作执行时记下它的逆操作 就可以撤销和重做 见 history.rs
+    history::demo();
 
     //matches!宏 可以用来简化match表达式
     let v = vec![IpAddrKind::V4, IpAddrKind::V6];
@@ -748,6 +826,10 @@ fn main() {
         sign_in_count: 1,
         active: true,
     };
+    //User手动实现了Debug和Display 可以直接打印 见 user_traits.rs
+    println!("user = {:?}", user);
+    println!("user = {}", user);
+    user_traits::demo();
     // let User { username:a, email:b, sign_in_count:c, active:d } = user;
     // //将结构体的字段解构到变量中
     // println!("a = {}, b = {}, c = {}, d = {}", a, b, c, d);
@@ -769,6 +851,7 @@ fn main() {
 
     //==================================================================方法==================================================================
 
+    #[derive(Describe)]
     struct Circle {
         x: f64,
         y: f64,
@@ -784,6 +867,10 @@ fn main() {
             std::f64::consts::PI * (self.radius * self.radius)
         }
     }
+    let circle = Circle::new(0.0, 0.0, 1.0);
+    //describe方法是 #[derive(Describe)] 生成的
+    println!("circle = {}, area = {:.2}", circle.describe(), circle.area());
+    assert_eq!(circle.describe(), "Circle { x: f64 = 0.0, y: f64 = 0.0, radius: f64 = 1.0 }");
 
     // self &self &mut self
     // 在area方法中，我们使用了&self，其实是self：&Self的简写 ，这是因为我们不想获取Circle的所有权，只是想借用它，这样Circle就不会被销毁
@@ -1009,6 +1096,8 @@ fn main() {
     //通过derive派生特征
     //形如 #[derive(Debug)]
     //例如 Debug 特征，它有一套自动实现的默认代码，当你给一个结构体标记后，就可以使用 println!("{:?}", s) 的形式打印该结构体的对象。
+    //这套默认代码是由派生宏在编译期生成的 describe_derive 中手写了一个派生宏 #[derive(Describe)] 见 describe.rs
+    describe::demo();
 
     //调用方法需要引入特征
     let a: i32 = 10;
@@ -1085,6 +1174,8 @@ fn main() {
         draw2(&x);
         draw2(&y);
     }
+    //Box之外的智能指针 多个Screen共享同一个Button(Rc<RefCell>) 用Weak指回父节点的树 以及跨线程的Arc<Mutex> 见 smart_pointers.rs
+    smart_pointers::demo();
 
     //接下来完善Screen的run方法 用于将列表中的组件渲染在屏幕上
     impl Screen {
@@ -1276,8 +1367,8 @@ fn main() {
     //存储不同类型的元素
     //通常情况下 数组中必须存储相同类型的元素 但是可以通过使用枚举和特征对象来实现不同类型元素的存储
     let v = vec![
-        IpAddr::V4("127.0.0.1".to_string()),
-        IpAddr::V6("::1".to_string()),
+        IpAddr::V4(Ipv4::LOCALHOST),
+        "::1".parse().unwrap(),
     ];
     for ip in v {
         // 数组中是不同的IPAddr枚举类型
@@ -1351,6 +1442,61 @@ fn main() {
     v.sort_by(|a, b| a.partial_cmp(b).unwrap());
 }
 
+fn run_command(args: &[String]) {
+    match args[0].as_str() {
+        "bench-text" => {
+            let n = args.get(1).and_then(|n| n.parse().ok()).unwrap_or(20_000);
+            gap_buffer::bench(n);
+        }
+        "cidr" => match args.get(1) {
+            Some(cidr) => {
+                if !cidr::print_breakdown(cidr) {
+                    std::process::exit(1);
+                }
+            }
+            None => {
+                eprintln!("用法: myrust cidr <地址/前缀长度>");
+                std::process::exit(2);
+            }
+        },
+        "check-ip" => {
+            if args.len() < 3 {
+                eprintln!("用法: myrust check-ip <白名单文件> <地址>...");
+                std::process::exit(2);
+            }
+            if !ip_range::check_file(&args[1], &args[2..]) {
+                std::process::exit(1);
+            }
+        }
+        "run-script" => match args.get(1) {
+            Some(path) => {
+                if !script::run_file(path, 16, 8) {
+                    std::process::exit(1);
+                }
+            }
+            None => {
+                eprintln!("用法: myrust run-script <file>");
+                std::process::exit(2);
+            }
+        },
+        "lint-names" => match args.get(1) {
+            Some(path) => {
+                if !naming::lint_file(path) {
+                    std::process::exit(1);
+                }
+            }
+            None => {
+                eprintln!("用法: myrust lint-names <file>");
+                std::process::exit(2);
+            }
+        },
+        other => {
+            eprintln!("未知的命令: {}", other);
+            std::process::exit(2);
+        }
+    }
+}
+
 fn plus_two(x: Option<i32>) -> Option<i32> {
     match x {
         None => None,
@@ -1358,17 +1504,17 @@ fn plus_two(x: Option<i32>) -> Option<i32> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 enum Action {
     Say(String),
     MoveTo(i32, i32),
     ChangeColor(i32, i32, i32),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum IpAddr {
-    V4(String),
-    V6(String),
+    V4(Ipv4),
+    V6(Ipv6),
 }
 
 fn show_ip(ip: IpAddr){
@@ -1377,7 +1523,7 @@ fn show_ip(ip: IpAddr){
 
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum IpAddrKind {
     V4,
     V6,
@@ -1391,13 +1537,12 @@ enum PokerCard {
     Hearts(u8),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Describe)]
 struct IpInfo {
-    kind: IpAddrKind,
-    address: String,
+    address: IpAddr,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Describe)]
 enum Message {
     Quit,
     Move { x: i32, y: i32 },
@@ -1423,6 +1568,7 @@ fn send_Msg(msg: Message) {
     println!("msg = {:?}", msg);
 }
 
+#[derive(Describe)]
 struct User {
     username: String,
     email: String,
@@ -1475,10 +1621,6 @@ fn calculate_length2(s: String) -> (String, usize) {
 //    &s // 返回字符串 s 的引用
 // } // 这里 s 离开作用域并被丢弃。其内存被释放。
 
-fn first_word(s: &String) -> &str {
-    &s[..1]
-}
-
 struct Struct {
     e: i32,
 }
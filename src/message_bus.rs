@@ -0,0 +1,326 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::Message;
+
+// 消息总线 send_Msg 只是打印 这里把"接收并转发任意类型的Message"变成真正的代码
+// 处理函数订阅某一种Message(或全部) publish只把消息放进队列 run按先进先出的顺序逐条分发
+// 处理函数可以通过Outbox发出后续消息 它们排在队尾 等当前消息分发完之后才处理
+// 同一条消息按订阅的先后顺序交给每个处理函数
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MessageKind {
+    Quit,
+    Move,
+    Write,
+    ChangeColor,
+}
+
+impl Message {
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            Message::Quit => MessageKind::Quit,
+            Message::Move { .. } => MessageKind::Move,
+            Message::Write(_) => MessageKind::Write,
+            Message::ChangeColor(..) => MessageKind::ChangeColor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topic {
+    Only(MessageKind),
+    All,
+}
+
+impl Topic {
+    fn matches(self, kind: MessageKind) -> bool {
+        match self {
+            Topic::Only(only) => only == kind,
+            Topic::All => true,
+        }
+    }
+}
+
+// subscribe返回的编号 用来取消订阅
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionId(usize);
+
+// 处理函数发出的后续消息先放在这里 当前消息分发完之后再统一放进队列
+#[derive(Default)]
+pub struct Outbox {
+    messages: Vec<Message>,
+}
+
+impl Outbox {
+    pub fn emit(&mut self, msg: Message) {
+        self.messages.push(msg);
+    }
+}
+
+type Handler = Box<dyn FnMut(&Message, &mut Outbox)>;
+
+struct Subscription {
+    id: SubscriptionId,
+    topic: Topic,
+    handler: Handler,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BusStats {
+    // 通过publish放进队列的消息
+    pub published: usize,
+    // 处理函数发出的后续消息
+    pub follow_ups: usize,
+    // 已经分发的消息 包括后续消息
+    pub dispatched: usize,
+    // 处理函数被调用的总次数 一条消息可能交给多个处理函数
+    pub deliveries: usize,
+    // 没有任何处理函数订阅的消息
+    pub unhandled: usize,
+    pub by_kind: BTreeMap<MessageKind, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BusError {
+    // 一次run分发的消息超过了上限 通常是处理函数互相发消息形成了死循环
+    LimitExceeded { limit: usize, pending: usize },
+}
+
+impl fmt::Display for BusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BusError::LimitExceeded { limit, pending } => {
+                write!(f, "一次分发超过了{}条消息 队列中还有{}条 可能存在消息循环", limit, pending)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BusError {}
+
+pub struct MessageBus {
+    subscriptions: Vec<Subscription>,
+    queue: VecDeque<Message>,
+    next_id: usize,
+    limit: usize,
+    stats: BusStats,
+}
+
+impl MessageBus {
+    pub fn new() -> MessageBus {
+        MessageBus { subscriptions: Vec::new(), queue: VecDeque::new(), next_id: 0, limit: 10_000, stats: BusStats::default() }
+    }
+
+    // 一次run最多分发多少条消息
+    pub fn with_limit(mut self, limit: usize) -> MessageBus {
+        self.limit = limit;
+        self
+    }
+
+    pub fn subscribe<F>(&mut self, topic: Topic, handler: F) -> SubscriptionId
+    where
+        F: FnMut(&Message, &mut Outbox) + 'static,
+    {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.subscriptions.push(Subscription { id, topic, handler: Box::new(handler) });
+        id
+    }
+
+    // 返回是否真的取消了 重复取消返回false
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let before = self.subscriptions.len();
+        self.subscriptions.retain(|s| s.id != id);
+        self.subscriptions.len() != before
+    }
+
+    pub fn publish(&mut self, msg: Message) {
+        self.stats.published += 1;
+        self.queue.push_back(msg);
+    }
+
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn stats(&self) -> &BusStats {
+        &self.stats
+    }
+
+    // 分发队列中的所有消息 包括分发过程中产生的后续消息 返回这次分发的消息条数
+    // 超过上限时停止 剩下的消息留在队列中
+    pub fn run(&mut self) -> Result<usize, BusError> {
+        let mut count = 0;
+        while let Some(msg) = self.queue.pop_front() {
+            if count == self.limit {
+                self.queue.push_front(msg);
+                return Err(BusError::LimitExceeded { limit: self.limit, pending: self.queue.len() });
+            }
+            count += 1;
+            self.dispatch(&msg);
+        }
+        Ok(count)
+    }
+
+    fn dispatch(&mut self, msg: &Message) {
+        let kind = msg.kind();
+        self.stats.dispatched += 1;
+        *self.stats.by_kind.entry(kind).or_insert(0) += 1;
+        let mut outbox = Outbox::default();
+        let mut delivered = 0;
+        for subscription in self.subscriptions.iter_mut().filter(|s| s.topic.matches(kind)) {
+            (subscription.handler)(msg, &mut outbox);
+            delivered += 1;
+        }
+        self.stats.deliveries += delivered;
+        if delivered == 0 {
+            self.stats.unhandled += 1;
+        }
+        self.stats.follow_ups += outbox.messages.len();
+        self.queue.extend(outbox.messages);
+    }
+}
+
+impl Default for MessageBus {
+    fn default() -> Self {
+        MessageBus::new()
+    }
+}
+
+type Log = Rc<RefCell<Vec<String>>>;
+
+// 课程中用到的总线: Move更新位置并报告新位置 Write记录文本 audit记录每条消息的类型
+// 处理函数是'static的闭包 用Rc<RefCell>在外面查看它们记录的内容
+fn lesson_bus() -> (MessageBus, Log, SubscriptionId) {
+    let mut bus = MessageBus::new();
+    let log: Log = Rc::new(RefCell::new(Vec::new()));
+
+    let mut position = (0, 0);
+    let l = Rc::clone(&log);
+    bus.subscribe(Topic::Only(MessageKind::Move), move |msg, outbox| {
+        if let Message::Move { x, y } = msg {
+            position = (position.0 + x, position.1 + y);
+            l.borrow_mut().push(format!("move -> {:?}", position));
+            // 移动之后报告新位置 这条消息排在队尾
+            outbox.emit(Message::Write(format!("位置 {:?}", position)));
+        }
+    });
+    let l = Rc::clone(&log);
+    bus.subscribe(Topic::Only(MessageKind::Write), move |msg, _| {
+        if let Message::Write(text) = msg {
+            l.borrow_mut().push(format!("write {}", text));
+        }
+    });
+    let l = Rc::clone(&log);
+    let audit = bus.subscribe(Topic::All, move |msg, _| l.borrow_mut().push(format!("audit {:?}", msg.kind())));
+    (bus, log, audit)
+}
+
+fn publish_lesson_messages(bus: &mut MessageBus) {
+    bus.publish(Message::Move { x: 1, y: 2 });
+    bus.publish(Message::ChangeColor(255, 0, 0));
+    bus.publish(Message::Move { x: -3, y: 0 });
+}
+
+// 两个处理函数互相发消息 会在达到上限时停下来
+fn ping_pong(limit: usize) -> MessageBus {
+    let mut bus = MessageBus::new().with_limit(limit);
+    bus.subscribe(Topic::Only(MessageKind::Quit), |_, outbox| outbox.emit(Message::Write(String::from("ping"))));
+    bus.subscribe(Topic::Only(MessageKind::Write), |_, outbox| outbox.emit(Message::Quit));
+    bus.publish(Message::Quit);
+    bus
+}
+
+pub fn demo() {
+    let (mut bus, log, audit) = lesson_bus();
+    publish_lesson_messages(&mut bus);
+    println!("队列中有{}条消息", bus.pending());
+    match bus.run() {
+        Ok(count) => println!("分发了{}条消息", count),
+        Err(err) => println!("{}", err),
+    }
+    for line in log.borrow().iter() {
+        println!("{}", line);
+    }
+
+    // 取消audit之后 Quit没有任何处理函数
+    println!("取消audit: {} 再取消一次: {}", bus.unsubscribe(audit), bus.unsubscribe(audit));
+    bus.publish(Message::Quit);
+    let _ = bus.run();
+    println!("{:?}", bus.stats());
+
+    let mut ping_pong = ping_pong(100);
+    if let Err(err) = ping_pong.run() {
+        println!("{} 已分发{}条", err, ping_pong.stats().dispatched);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 后续消息排在队尾 等先发布的消息都分发完之后才处理
+    #[test]
+    fn dispatches_fifo_with_follow_ups() {
+        let (mut bus, log, _) = lesson_bus();
+        publish_lesson_messages(&mut bus);
+        assert_eq!(bus.pending(), 3);
+        assert_eq!(bus.run(), Ok(5));
+        assert_eq!(bus.pending(), 0);
+        assert_eq!(
+            *log.borrow(),
+            [
+                "move -> (1, 2)",
+                "audit Move",
+                "audit ChangeColor",
+                "move -> (-2, 2)",
+                "audit Move",
+                "write 位置 (1, 2)",
+                "audit Write",
+                "write 位置 (-2, 2)",
+                "audit Write",
+            ]
+        );
+    }
+
+    #[test]
+    fn unsubscribe_twice() {
+        let (mut bus, log, audit) = lesson_bus();
+        assert!(bus.unsubscribe(audit));
+        assert!(!bus.unsubscribe(audit));
+        bus.publish(Message::ChangeColor(0, 0, 0));
+        assert_eq!(bus.run(), Ok(1));
+        assert!(log.borrow().is_empty());
+        assert_eq!(bus.stats().unhandled, 1);
+    }
+
+    #[test]
+    fn counts_stats() {
+        let (mut bus, _, audit) = lesson_bus();
+        publish_lesson_messages(&mut bus);
+        assert_eq!(bus.run(), Ok(5));
+        bus.unsubscribe(audit);
+        bus.publish(Message::Quit);
+        assert_eq!(bus.run(), Ok(1));
+        let stats = bus.stats();
+        assert_eq!((stats.published, stats.follow_ups, stats.dispatched, stats.deliveries, stats.unhandled), (4, 2, 6, 9, 1));
+        assert_eq!(stats.by_kind.get(&MessageKind::Move), Some(&2));
+        assert_eq!(stats.by_kind.get(&MessageKind::Write), Some(&2));
+        assert_eq!(stats.by_kind.get(&MessageKind::Quit), Some(&1));
+    }
+
+    // 超过上限时停止 没有分发的消息留在队列中
+    #[test]
+    fn limit_exceeded_leaves_message_queued() {
+        let mut bus = ping_pong(100);
+        assert_eq!(bus.run(), Err(BusError::LimitExceeded { limit: 100, pending: 1 }));
+        assert_eq!(bus.stats().dispatched, 100);
+        assert_eq!(bus.pending(), 1);
+        // 再次run会从留下的消息继续
+        assert_eq!(bus.run(), Err(BusError::LimitExceeded { limit: 100, pending: 1 }));
+        assert_eq!(bus.stats().dispatched, 200);
+    }
+}
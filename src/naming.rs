@@ -0,0 +1,498 @@
+use std::fmt;
+
+// 标识符的命名风格转换 以及一个简单的命名规范检查器
+// Rust的约定: 函数、变量、模块、字段用 snake_case 类型、特征、枚举成员用 PascalCase(UpperCamelCase)
+// 常量和静态变量用 SCREAMING_SNAKE_CASE 编译器会对不符合约定的名字给出 non_snake_case 等警告
+
+// 把标识符拆成单词 支持 snake_case kebab-case camelCase PascalCase 以及它们的混合
+// 连续的大写字母被视为一个缩写词: HTTPServer => [http, server]  数字跟随前面的单词: IpV4Addr => [ip, v4, addr]
+pub fn split_words(ident: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in ident.split(|c: char| !c.is_alphanumeric()).filter(|p| !p.is_empty()) {
+        let chars: Vec<char> = part.chars().collect();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            let prev = i.checked_sub(1).map(|p| chars[p]);
+            let next = chars.get(i + 1);
+            let boundary = c.is_uppercase()
+                && match prev {
+                    Some(p) if p.is_lowercase() || p.is_ascii_digit() => true,
+                    Some(p) if p.is_uppercase() => next.is_some_and(|n| n.is_lowercase()),
+                    _ => false,
+                };
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.extend(c.to_lowercase());
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub fn to_snake_case(ident: &str) -> String {
+    split_words(ident).join("_")
+}
+
+pub fn to_kebab_case(ident: &str) -> String {
+    split_words(ident).join("-")
+}
+
+pub fn to_screaming_snake_case(ident: &str) -> String {
+    to_snake_case(ident).to_uppercase()
+}
+
+pub fn to_pascal_case(ident: &str) -> String {
+    split_words(ident).iter().map(|w| capitalize(w)).collect()
+}
+
+pub fn to_camel_case(ident: &str) -> String {
+    let pascal = to_pascal_case(ident);
+    let mut chars = pascal.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Snake,
+    Pascal,
+    ScreamingSnake,
+}
+
+impl Style {
+    // 前后的下划线不影响风格 例如 _unused 和 r#type
+    fn matches(self, ident: &str) -> bool {
+        let ident = ident.trim_matches('_');
+        match self {
+            Style::Snake => !ident.chars().any(char::is_uppercase) && !ident.contains("__"),
+            // 和编译器一样允许 HTTPServer 这样的缩写
+            Style::Pascal => !ident.contains('_') && !ident.starts_with(char::is_lowercase),
+            Style::ScreamingSnake => !ident.chars().any(char::is_lowercase) && !ident.contains("__"),
+        }
+    }
+
+    fn convert(self, ident: &str) -> String {
+        // 保留开头的下划线 _unused_var 仍然表示有意不使用
+        let prefix: String = ident.chars().take_while(|&c| c == '_').collect();
+        let converted = match self {
+            Style::Snake => to_snake_case(ident),
+            Style::Pascal => to_pascal_case(ident),
+            Style::ScreamingSnake => to_screaming_snake_case(ident),
+        };
+        prefix + &converted
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Style::Snake => "snake_case",
+            Style::Pascal => "PascalCase",
+            Style::ScreamingSnake => "SCREAMING_SNAKE_CASE",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub line: usize,
+    pub column: usize,
+    // 函数、结构体、枚举成员...
+    pub item: &'static str,
+    pub name: String,
+    pub expected: Style,
+    pub suggestion: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} `{}` 应该使用 {} 命名: `{}`",
+            self.line, self.column, self.item, self.name, self.expected, self.suggestion
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Punct(char),
+}
+
+// 极简的词法分析 只关心标识符和括号等符号 跳过注释、字符串、字符字面量和生命周期
+fn tokenize(src: &str) -> Vec<(Token, usize, usize)> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line, mut col) = (0, 1, 1);
+    // 前进n个字符 同时维护行号和列号
+    let advance = |i: &mut usize, line: &mut usize, col: &mut usize, n: usize| {
+        for _ in 0..n {
+            if chars.get(*i) == Some(&'\n') {
+                *line += 1;
+                *col = 1;
+            } else {
+                *col += 1;
+            }
+            *i += 1;
+        }
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let rest = &chars[i..];
+        if rest.starts_with(&['/', '/']) {
+            let len = rest.iter().position(|&c| c == '\n').unwrap_or(rest.len());
+            advance(&mut i, &mut line, &mut col, len);
+        } else if rest.starts_with(&['/', '*']) {
+            // 块注释可以嵌套
+            let (mut depth, mut j) = (0, 0);
+            while j < rest.len() {
+                if rest[j..].starts_with(&['/', '*']) {
+                    depth += 1;
+                    j += 2;
+                } else if rest[j..].starts_with(&['*', '/']) {
+                    depth -= 1;
+                    j += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    j += 1;
+                }
+            }
+            advance(&mut i, &mut line, &mut col, j);
+        } else if c == '"' || ((c == 'r' || c == 'b') && raw_or_byte_string_len(rest).is_some()) {
+            let len = raw_or_byte_string_len(rest).unwrap_or_else(|| string_len(rest));
+            advance(&mut i, &mut line, &mut col, len);
+        } else if c == '\'' {
+            // 'a' '\n' 是字符字面量 'a 是生命周期
+            let len = if rest.get(1) == Some(&'\\') {
+                // 转义序列至少占两个字符 结束的引号从第4个字符开始找 这样 '\'' 也能正确处理
+                rest.iter().skip(3).position(|&c| c == '\'').map_or(rest.len(), |p| p + 4)
+            } else if rest.get(2) == Some(&'\'') {
+                3
+            } else {
+                1 + rest[1..].iter().take_while(|c| c.is_alphanumeric() || **c == '_').count()
+            };
+            advance(&mut i, &mut line, &mut col, len);
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest.iter().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+            let mut ident: String = rest[..len].iter().collect();
+            let (start_line, start_col) = (line, col);
+            advance(&mut i, &mut line, &mut col, len);
+            // 原始标识符 r#type
+            if ident == "r" && chars.get(i) == Some(&'#') {
+                let len = chars[i + 1..].iter().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+                ident = chars[i + 1..i + 1 + len].iter().collect();
+                advance(&mut i, &mut line, &mut col, len + 1);
+            }
+            tokens.push((Token::Ident(ident), start_line, start_col));
+        } else if c.is_numeric() {
+            let len = rest.iter().take_while(|c| c.is_alphanumeric() || **c == '_' || **c == '.').count();
+            advance(&mut i, &mut line, &mut col, len);
+        } else {
+            if !c.is_whitespace() {
+                tokens.push((Token::Punct(c), line, col));
+            }
+            advance(&mut i, &mut line, &mut col, 1);
+        }
+    }
+    tokens
+}
+
+// 普通字符串 "..." 的长度(含引号)
+fn string_len(rest: &[char]) -> usize {
+    let mut j = 1;
+    while j < rest.len() {
+        match rest[j] {
+            '\\' => j += 2,
+            '"' => return j + 1,
+            _ => j += 1,
+        }
+    }
+    rest.len()
+}
+
+// r"..." r#"..."# b"..." br"..." 的长度 不是这几种形式时返回None
+fn raw_or_byte_string_len(rest: &[char]) -> Option<usize> {
+    let prefix = if rest.starts_with(&['b', 'r']) {
+        2
+    } else if rest.starts_with(&['r']) || rest.starts_with(&['b']) {
+        1
+    } else {
+        return None;
+    };
+    if rest[0] == 'b' && prefix == 1 {
+        return (rest.get(1) == Some(&'"')).then(|| 1 + string_len(&rest[1..]));
+    }
+    let hashes = rest[prefix..].iter().take_while(|&&c| c == '#').count();
+    if rest.get(prefix + hashes) != Some(&'"') {
+        return None;
+    }
+    let body = prefix + hashes + 1;
+    let closing: Vec<char> = std::iter::once('"').chain(std::iter::repeat_n('#', hashes)).collect();
+    let end = (body..rest.len()).find(|&j| rest[j..].starts_with(&closing))?;
+    Some(end + closing.len())
+}
+
+// 正在解析的括号 记录它属于哪种条目 用来识别枚举成员和结构体字段
+#[derive(Debug, PartialEq)]
+enum Frame {
+    EnumBody,
+    StructBody,
+    // impl Trait for Type 里的方法名由特征决定 不在这里检查
+    TraitImpl,
+    Other,
+}
+
+pub fn lint_source(src: &str) -> Vec<Violation> {
+    let tokens = tokenize(src);
+    let mut violations = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    // 下一个 { 打开的是什么
+    let mut pending = Frame::Other;
+    // 在枚举/结构体体内 下一个标识符是否是成员名
+    let mut expect_member = false;
+
+    let ident_at = |i: usize| match tokens.get(i) {
+        Some((Token::Ident(name), _, _)) => Some(name.as_str()),
+        _ => None,
+    };
+    let punct_at = |i: usize| match tokens.get(i) {
+        Some((Token::Punct(c), _, _)) => Some(*c),
+        _ => None,
+    };
+    let mut check = |i: usize, item: &'static str, style: Style| {
+        if let Some((Token::Ident(name), line, column)) = tokens.get(i) {
+            if !style.matches(name) {
+                violations.push(Violation {
+                    line: *line,
+                    column: *column,
+                    item,
+                    name: name.clone(),
+                    expected: style,
+                    suggestion: style.convert(name),
+                });
+            }
+        }
+    };
+
+    for i in 0..tokens.len() {
+        let in_trait_impl = stack.contains(&Frame::TraitImpl);
+        match &tokens[i].0 {
+            Token::Punct('{') => {
+                stack.push(std::mem::replace(&mut pending, Frame::Other));
+                expect_member = matches!(stack.last(), Some(Frame::EnumBody | Frame::StructBody));
+            }
+            // 圆括号和方括号里的逗号不是成员之间的分隔 例如 ChangeColor(i32, i32, i32)
+            Token::Punct('(' | '[') => {
+                stack.push(Frame::Other);
+                expect_member = false;
+            }
+            Token::Punct('}' | ')' | ']') => {
+                stack.pop();
+                expect_member = false;
+            }
+            Token::Punct(',') => {
+                expect_member = matches!(stack.last(), Some(Frame::EnumBody | Frame::StructBody));
+            }
+            Token::Punct(';') => pending = Frame::Other,
+            Token::Ident(kw) if expect_member && kw != "pub" && kw != "crate" => {
+                expect_member = false;
+                match stack.last() {
+                    Some(Frame::EnumBody) => check(i, "枚举成员", Style::Pascal),
+                    _ if punct_at(i + 1) == Some(':') => check(i, "字段", Style::Snake),
+                    _ => {}
+                }
+            }
+            Token::Ident(kw) => match kw.as_str() {
+                "fn" if !in_trait_impl => check(i + 1, "函数", Style::Snake),
+                "mod" => check(i + 1, "模块", Style::Snake),
+                "struct" | "union" => {
+                    check(i + 1, "结构体", Style::Pascal);
+                    pending = Frame::StructBody;
+                }
+                "enum" => {
+                    check(i + 1, "枚举", Style::Pascal);
+                    pending = Frame::EnumBody;
+                }
+                "trait" => check(i + 1, "特征", Style::Pascal),
+                "type" if ident_at(i.wrapping_sub(1)) != Some("impl") => check(i + 1, "类型别名", Style::Pascal),
+                "const" if ident_at(i + 1) != Some("fn") => check(i + 1, "常量", Style::ScreamingSnake),
+                "static" => {
+                    let j = if ident_at(i + 1) == Some("mut") { i + 2 } else { i + 1 };
+                    check(j, "静态变量", Style::ScreamingSnake);
+                }
+                "let" => {
+                    let j = if ident_at(i + 1) == Some("mut") { i + 2 } else { i + 1 };
+                    // 只检查简单的绑定 let x = / let x: 解构模式和 let Action::Say(s) 这样的路径跳过
+                    let is_path = punct_at(j + 1) == Some(':') && punct_at(j + 2) == Some(':');
+                    if matches!(punct_at(j + 1), Some('=' | ':' | ';')) && !is_path {
+                        check(j, "变量", Style::Snake);
+                    }
+                }
+                "impl" => {
+                    // impl Trait for Type { 之前出现了 for 就是为类型实现特征
+                    let is_trait_impl = tokens[i + 1..]
+                        .iter()
+                        .take_while(|(t, _, _)| *t != Token::Punct('{') && *t != Token::Punct(';'))
+                        .any(|(t, _, _)| *t == Token::Ident("for".to_string()));
+                    pending = if is_trait_impl { Frame::TraitImpl } else { Frame::Other };
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    violations
+}
+
+pub fn demo() {
+    for ident in ["print_IpAddrKind", "send_Msg", "summarizeDefalt", "Point_test", "HTTPServer", "ip-v4-addr"] {
+        println!(
+            "{:<18} snake: {:<20} camel: {:<18} pascal: {:<18} screaming: {:<20} kebab: {}",
+            ident,
+            to_snake_case(ident),
+            to_camel_case(ident),
+            to_pascal_case(ident),
+            to_screaming_snake_case(ident),
+            to_kebab_case(ident)
+        );
+    }
+
+    // 检查这份课程代码本身 上面讲了函数名必须是snake_case 但下面有几个名字并不符合
+    // 完整的检查可以运行 cargo run -- lint-names src/main.rs
+    let violations = lint_source(include_str!("main.rs"));
+    println!("main.rs 中有{}个名字不符合命名规范 例如:", violations.len());
+    for v in violations.iter().take(3) {
+        println!("  main.rs:{}", v);
+    }
+}
+
+// cargo run -- lint-names <file>  有不符合规范的名字时返回false
+pub fn lint_file(path: &str) -> bool {
+    let src = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) => {
+            eprintln!("无法读取 {}: {}", path, e);
+            return false;
+        }
+    };
+    let violations = lint_source(&src);
+    for v in &violations {
+        println!("{}:{}", path, v);
+    }
+    violations.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(src: &str) -> Vec<(String, usize, usize)> {
+        lint_source(src).into_iter().map(|v| (v.name, v.line, v.column)).collect()
+    }
+
+    #[test]
+    fn converts_between_styles() {
+        assert_eq!(to_snake_case("print_IpAddrKind"), "print_ip_addr_kind");
+        assert_eq!(to_pascal_case("Point_test"), "PointTest");
+        assert_eq!(to_camel_case("summarize_default"), "summarizeDefault");
+        assert_eq!(to_screaming_snake_case("maxValue"), "MAX_VALUE");
+        assert_eq!(to_kebab_case("IpAddrKind"), "ip-addr-kind");
+        assert_eq!(to_camel_case(""), "");
+    }
+
+    #[test]
+    fn keeps_acronyms_together() {
+        assert_eq!(split_words("HTTPServer"), ["http", "server"]);
+        assert_eq!(to_snake_case("parseJSONValue"), "parse_json_value");
+        assert_eq!(to_snake_case("XMLHttpRequest"), "xml_http_request");
+        assert_eq!(to_pascal_case("http_server"), "HttpServer");
+        assert_eq!(to_snake_case("URL"), "url");
+    }
+
+    #[test]
+    fn digits_follow_previous_word() {
+        assert_eq!(to_snake_case("IpV4Addr"), "ip_v4_addr");
+        assert_eq!(to_pascal_case("ip_v4_addr"), "IpV4Addr");
+        assert_eq!(to_snake_case("utf8Len"), "utf8_len");
+        assert_eq!(to_snake_case("sha256Sum"), "sha256_sum");
+        assert_eq!(split_words("v2"), ["v2"]);
+    }
+
+    #[test]
+    fn reports_items_with_position() {
+        let src = "fn badName() {}\nstruct lower_case { fieldName: u8 }\nenum Kind { snake_variant }\nconst maxSize: u8 = 1;\n";
+        let violations = lint_source(src);
+        assert_eq!(violations.len(), 5, "{:?}", violations);
+        assert_eq!(names(src)[0], (String::from("badName"), 1, 4));
+        assert!(violations.iter().any(|v| v.name == "fieldName" && v.expected == Style::Snake));
+        assert!(violations.iter().any(|v| v.name == "snake_variant" && v.expected == Style::Pascal));
+        assert!(violations.iter().any(|v| v.name == "maxSize" && v.suggestion == "MAX_SIZE"));
+    }
+
+    // 为外部特征实现的方法名由特征决定 rustc 只在特征定义处报告 这里也一样
+    #[test]
+    fn skips_trait_impl_methods() {
+        let src = "trait Summary { fn summarizeAuthor(&self); }\n\
+                   impl Summary for Tweet { fn summarizeAuthor(&self) {} }\n\
+                   impl Tweet { fn otherName(&self) {} }\n";
+        let found: Vec<String> = names(src).into_iter().map(|(name, _, _)| name).collect();
+        assert_eq!(found, ["summarizeAuthor", "otherName"]);
+        assert_eq!(names(src)[0].1, 1);
+    }
+
+    #[test]
+    fn ignores_strings_comments_and_raw_strings() {
+        let src = r##"let s = "fn badName() {}";
+let r = r#"fn alsoBad() { "quoted" }"#;
+let b = br"struct lower {}";
+// fn commentBad() {}
+/* fn blockBad() {} */
+fn realBad() {}
+"##;
+        assert_eq!(names(src), [(String::from("realBad"), 6, 4)]);
+    }
+
+    // 生命周期和字符字面量都以'开头 不能把后面的内容当作字符字面量吞掉
+    #[test]
+    fn handles_lifetimes_and_chars() {
+        let src = "struct Holder<'a> { text: &'a str }\n\
+                   fn first<'a>(s: &'a str) -> char { let c = '{'; let q = '\\''; c }\n\
+                   fn afterLifetime() {}\n";
+        assert_eq!(names(src), [(String::from("afterLifetime"), 3, 4)]);
+    }
+
+    // 和 rustc 对 main.rs 给出的 non_snake_case / non_camel_case_types 警告一致
+    #[test]
+    fn matches_rustc_on_main() {
+        let src = include_str!("main.rs");
+        let violations = lint_source(src);
+        let found: Vec<&str> = violations.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(
+            found,
+            ["summarizeDefalt", "Point_test", "print_IpAddrKind", "print_IpInfo", "print_PokerCards", "send_Msg"]
+        );
+        // 报告的位置确实指向这个名字
+        for v in &violations {
+            let line = src.lines().nth(v.line - 1).unwrap();
+            let rest: String = line.chars().skip(v.column - 1).collect();
+            assert!(rest.starts_with(&v.name), "{}", v);
+        }
+    }
+}
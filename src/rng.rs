@@ -0,0 +1,43 @@
+// 一个极简的 xorshift64 伪随机数生成器
+// 标准库没有提供随机数 这里只用于在单元测试中生成随机输入做性质检查 不要用于任何安全场景
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        // 状态不能为0 否则会一直输出0
+        XorShift { state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    // 返回 0..n 之间的数 n必须大于0
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // 随机生成一个合法的unicode字符 刻意偏向ASCII、汉字和emoji 方便覆盖1~4字节的UTF-8编码
+    pub fn next_char(&mut self) -> char {
+        let c = match self.below(4) {
+            0 => self.below(0x80) as u32,
+            1 => 0x4E00 + self.below(0x51A0) as u32,
+            2 => 0x1F600 + self.below(0x50) as u32,
+            _ => self.below(0x11_0000) as u32,
+        };
+        // 代理区的码点不是合法的char 退回到替换字符
+        char::from_u32(c).unwrap_or('\u{FFFD}')
+    }
+
+    pub fn next_string(&mut self, max_len: usize) -> String {
+        let len = self.below(max_len + 1);
+        (0..len).map(|_| self.next_char()).collect()
+    }
+}
@@ -0,0 +1,247 @@
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+// 按字符（而不是字节）索引的安全字符串操作
+// 标准库的 &s[a..b]、remove、insert、truncate 都使用字节索引 索引不在字符边界上时会直接panic
+// 下面的扩展特征把索引换成字符下标 越界时返回Option/Result 而不是panic
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CharIndexError {
+    // 字符下标超出了字符串的字符数
+    OutOfBounds { index: usize, char_len: usize },
+}
+
+impl fmt::Display for CharIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CharIndexError::OutOfBounds { index, char_len } => {
+                write!(f, "字符下标{}越界 字符串只有{}个字符", index, char_len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CharIndexError {}
+
+// 只读操作 同时适用于 &str 和 String（String 会自动解引用为 str）
+pub trait CharSlice {
+    // 字符个数 注意 len() 返回的是字节数
+    fn char_len(&self) -> usize;
+    // 第char_idx个字符的起始字节偏移 char_idx等于字符数时返回字符串的字节长度
+    fn char_to_byte(&self, char_idx: usize) -> Option<usize>;
+    // 按字符下标切片 越界或者start > end 返回None
+    fn slice_chars<R: RangeBounds<usize>>(&self, range: R) -> Option<&str>;
+    // 按字符下标切片 越界的部分被截断到字符串末尾 永远不会panic
+    fn slice_chars_clamped<R: RangeBounds<usize>>(&self, range: R) -> &str;
+    // 按字节下标切片 但把两端向前收缩到最近的字符边界 例如 "你好"[1..4] 会得到 "你"
+    fn slice_bytes_clamped<R: RangeBounds<usize>>(&self, range: R) -> &str;
+}
+
+// 修改操作 只适用于 String
+pub trait CharEdit {
+    // 只保留前n个字符 n超过字符数时什么也不做
+    fn truncate_chars(&mut self, n: usize);
+    // 删除并返回第idx个字符
+    fn remove_char_at(&mut self, idx: usize) -> Result<char, CharIndexError>;
+    // 在第idx个字符前插入 idx等于字符数时追加到末尾
+    fn insert_at_char(&mut self, idx: usize, ch: char) -> Result<(), CharIndexError>;
+    fn insert_str_at_char(&mut self, idx: usize, s: &str) -> Result<(), CharIndexError>;
+}
+
+// 把RangeBounds转换成[start, end) 的形式 end为None表示直到末尾
+fn bounds<R: RangeBounds<usize>>(range: &R) -> (usize, Option<usize>) {
+    let start = match range.start_bound() {
+        Bound::Included(&s) => s,
+        Bound::Excluded(&s) => s.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&e) => Some(e.saturating_add(1)),
+        Bound::Excluded(&e) => Some(e),
+        Bound::Unbounded => None,
+    };
+    (start, end)
+}
+
+// 小于等于index的最近字符边界
+fn floor_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    let mut i = index;
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+impl CharSlice for str {
+    fn char_len(&self) -> usize {
+        self.chars().count()
+    }
+
+    fn char_to_byte(&self, char_idx: usize) -> Option<usize> {
+        self.char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(self.len()))
+            .nth(char_idx)
+    }
+
+    fn slice_chars<R: RangeBounds<usize>>(&self, range: R) -> Option<&str> {
+        let (start, end) = bounds(&range);
+        let start_byte = self.char_to_byte(start)?;
+        let end_byte = match end {
+            Some(end) if end < start => return None,
+            // 从start_byte开始继续数 避免再从头遍历一遍
+            Some(end) => start_byte + self[start_byte..].char_to_byte(end - start)?,
+            None => self.len(),
+        };
+        Some(&self[start_byte..end_byte])
+    }
+
+    fn slice_chars_clamped<R: RangeBounds<usize>>(&self, range: R) -> &str {
+        let (start, end) = bounds(&range);
+        let len = self.char_len();
+        let start = start.min(len);
+        let end = end.unwrap_or(len).clamp(start, len);
+        // 上面已经把下标收缩到合法范围 这里一定是Some
+        self.slice_chars(start..end).unwrap_or("")
+    }
+
+    fn slice_bytes_clamped<R: RangeBounds<usize>>(&self, range: R) -> &str {
+        let (start, end) = bounds(&range);
+        let start = floor_boundary(self, start);
+        let end = floor_boundary(self, end.unwrap_or(self.len())).max(start);
+        &self[start..end]
+    }
+}
+
+impl CharEdit for String {
+    fn truncate_chars(&mut self, n: usize) {
+        if let Some(byte) = self.char_to_byte(n) {
+            self.truncate(byte);
+        }
+    }
+
+    fn remove_char_at(&mut self, idx: usize) -> Result<char, CharIndexError> {
+        match self.char_indices().nth(idx) {
+            Some((byte, _)) => Ok(self.remove(byte)),
+            None => Err(CharIndexError::OutOfBounds { index: idx, char_len: self.char_len() }),
+        }
+    }
+
+    fn insert_at_char(&mut self, idx: usize, ch: char) -> Result<(), CharIndexError> {
+        let byte = self
+            .char_to_byte(idx)
+            .ok_or(CharIndexError::OutOfBounds { index: idx, char_len: self.char_len() })?;
+        self.insert(byte, ch);
+        Ok(())
+    }
+
+    fn insert_str_at_char(&mut self, idx: usize, s: &str) -> Result<(), CharIndexError> {
+        let byte = self
+            .char_to_byte(idx)
+            .ok_or(CharIndexError::OutOfBounds { index: idx, char_len: self.char_len() })?;
+        self.insert_str(byte, s);
+        Ok(())
+    }
+}
+
+pub fn demo() {
+    let s = "你好世界";
+    //&s[2..] 会panic 按字符下标切片则没有问题
+    println!("slice_chars(2..) = {:?}", s.slice_chars(2..));
+    println!("slice_chars(1..9) = {:?}", s.slice_chars(1..9)); // 越界 返回None
+    println!("slice_chars_clamped(1..9) = {}", s.slice_chars_clamped(1..9));
+    println!("slice_bytes_clamped(2..) = {}", s.slice_bytes_clamped(2..)); // 字节2落在'你'内部 收缩到0
+
+    // 对应上面 s.remove(0) 和 s.truncate(3) 的例子 这里下标都是字符下标
+    let mut s = String::from("你好你好");
+    println!("remove_char_at(0) = {:?}", s.remove_char_at(0));
+    println!("remove_char_at(2) = {:?}", s.remove_char_at(2)); // s.remove(2) 会panic
+    println!("s = {}", s);
+    s.truncate_chars(1);
+    println!("s = {}", s);
+    let _ = s.insert_at_char(1, '!');
+    let _ = s.insert_str_at_char(0, "Rust");
+    println!("s = {}", s);
+    match s.remove_char_at(10) {
+        Ok(c) => println!("removed {}", c),
+        Err(e) => println!("error: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+
+    #[test]
+    fn slices_by_char_index() {
+        let s = "你好世界";
+        assert_eq!(s.slice_chars(2..), Some("世界"));
+        assert_eq!(s.slice_chars(1..9), None);
+        // start > end 也返回None
+        let (start, end) = (3, 1);
+        assert_eq!(s.slice_chars(start..end), None);
+        assert_eq!(s.slice_chars_clamped(1..9), "好世界");
+        assert_eq!(s.slice_bytes_clamped(2..), "你好世界");
+        assert_eq!("你好".slice_bytes_clamped(1..4), "你");
+        assert_eq!(s.char_len(), 4);
+        assert_eq!(s.char_to_byte(4), Some(12));
+        assert_eq!(s.char_to_byte(5), None);
+    }
+
+    #[test]
+    fn edits_by_char_index() {
+        let mut s = String::from("你好你好");
+        assert_eq!(s.remove_char_at(0), Ok('你'));
+        assert_eq!(s.remove_char_at(2), Ok('好'));
+        assert_eq!(s, "好你");
+        s.truncate_chars(1);
+        assert_eq!(s, "好");
+        assert_eq!(s.insert_at_char(1, '!'), Ok(()));
+        assert_eq!(s.insert_str_at_char(0, "Rust"), Ok(()));
+        assert_eq!(s, "Rust好!");
+        assert_eq!(s.remove_char_at(10), Err(CharIndexError::OutOfBounds { index: 10, char_len: 6 }));
+        assert_eq!(s.insert_at_char(7, '?'), Err(CharIndexError::OutOfBounds { index: 7, char_len: 6 }));
+    }
+
+    // 用随机unicode字符串做性质检查：和先collect成Vec<char>再操作的结果一致
+    #[test]
+    fn matches_vec_char_model() {
+        let mut rng = XorShift::new(2023);
+        for _ in 0..500 {
+            let s = rng.next_string(12);
+            let chars: Vec<char> = s.chars().collect();
+            let a = rng.below(chars.len() + 3);
+            let b = rng.below(chars.len() + 3);
+
+            let expected: Option<String> = if a <= b && b <= chars.len() {
+                Some(chars[a..b].iter().collect())
+            } else {
+                None
+            };
+            assert_eq!(s.slice_chars(a..b).map(String::from), expected);
+
+            // 两个收缩版本永远不会panic 结果一定是原字符串的一部分
+            assert!(s.contains(s.slice_chars_clamped(a..b)));
+            assert!(s.contains(s.slice_bytes_clamped(a..b)));
+
+            let mut edited = s.clone();
+            let mut model = chars.clone();
+            match edited.remove_char_at(a) {
+                Ok(c) => assert_eq!(c, model.remove(a)),
+                Err(_) => assert!(a >= model.len()),
+            }
+            let c = rng.next_char();
+            match edited.insert_at_char(b, c) {
+                Ok(()) => model.insert(b, c),
+                Err(_) => assert!(b > model.len()),
+            }
+            edited.truncate_chars(a);
+            model.truncate(a);
+            assert_eq!(edited, model.iter().collect::<String>());
+        }
+    }
+}
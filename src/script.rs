@@ -0,0 +1,383 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::escape::{escape_str, parse_literal, to_literal, LiteralError, LiteralValue};
+use crate::turtle::Canvas;
+use crate::{Action, Message};
+
+// 一个按行解析的小脚本语言 用来代替代码里写死的Action/Message数组
+//   say "hello"       => Action::Say / Message::Write
+//   move 1 2          => Action::MoveTo / Message::Move
+//   color 255 255 0   => Action::ChangeColor / Message::ChangeColor
+//   quit              => Message::Quit (Action没有对应的成员)
+// 每行一条命令 参数用空白分隔 字符串的写法和Rust的字符串字面量相同(支持转义和r"原始字符串")
+// #开始到行尾是注释 空行会被忽略 出错时报告行号和列号(按字符计数 都从1开始)
+
+#[derive(Debug, PartialEq)]
+pub enum ScriptErrorKind {
+    UnknownCommand(String),
+    // 参数个数不对
+    ArgumentCount { command: &'static str, expected: usize, found: usize },
+    ExpectedNumber(String),
+    ExpectedString(String),
+    InvalidString(LiteralError),
+    // 命令不能转换成目标类型 例如Action没有quit
+    Unsupported { command: &'static str, target: &'static str },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ScriptErrorKind,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match &self.kind {
+            ScriptErrorKind::UnknownCommand(name) => write!(f, "未知的命令 {}", name),
+            ScriptErrorKind::ArgumentCount { command, expected, found } => {
+                write!(f, "{}需要{}个参数 但是有{}个", command, expected, found)
+            }
+            ScriptErrorKind::ExpectedNumber(text) => write!(f, "{} 不是合法的整数", text),
+            ScriptErrorKind::ExpectedString(text) => write!(f, "{} 应该是带引号的字符串", text),
+            ScriptErrorKind::InvalidString(err) => write!(f, "字符串不合法: {}", err),
+            ScriptErrorKind::Unsupported { command, target } => write!(f, "{} 不能用于{}", command, target),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+// 一行中的一个词或者一个字符串 column是它的第一个字符所在的列
+struct Token<'a> {
+    text: &'a str,
+    column: usize,
+    quoted: bool,
+}
+
+// 字符串从start开始 返回结束引号之后的字节位置 找不到结束引号时返回行尾 交给parse_literal报告错误
+fn string_end(line: &str, start: usize) -> usize {
+    let rest = &line[start..];
+    if let Some(raw) = rest.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let body = 1 + hashes + 1;
+        return match rest.get(body..).and_then(|s| s.find(&closing)) {
+            Some(i) => start + body + i + closing.len(),
+            None => line.len(),
+        };
+    }
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return start + i + 1,
+            _ => {}
+        }
+    }
+    line.len()
+}
+
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while let Some(c) = line[i..].chars().next() {
+        if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        }
+        if c == '#' {
+            break;
+        }
+        let rest = &line[i..];
+        let quoted = c == '"' || rest.starts_with("r\"") || rest.starts_with("r#");
+        let end = if quoted {
+            string_end(line, i)
+        } else {
+            rest.find(|c: char| c.is_whitespace() || c == '"' || c == '#').map_or(line.len(), |n| i + n)
+        };
+        tokens.push(Token { text: &line[i..end], column: line[..i].chars().count() + 1, quoted });
+        i = end;
+    }
+    tokens
+}
+
+// 与目标类型无关的命令 先解析成它 再转换成Action或Message
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Say(String),
+    Move(i32, i32),
+    Color(i32, i32, i32),
+    Quit,
+}
+
+impl Command {
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Say(_) => "say",
+            Command::Move(..) => "move",
+            Command::Color(..) => "color",
+            Command::Quit => "quit",
+        }
+    }
+}
+
+fn number(token: &Token<'_>, line: usize) -> Result<i32, ScriptError> {
+    let error = || ScriptError { line, column: token.column, kind: ScriptErrorKind::ExpectedNumber(token.text.to_string()) };
+    if token.quoted {
+        return Err(error());
+    }
+    token.text.parse().map_err(|_| error())
+}
+
+fn string(token: &Token<'_>, line: usize) -> Result<String, ScriptError> {
+    if !token.quoted {
+        return Err(ScriptError { line, column: token.column, kind: ScriptErrorKind::ExpectedString(token.text.to_string()) });
+    }
+    match parse_literal(token.text) {
+        Ok(LiteralValue::Str(s)) => Ok(s),
+        // tokenize只会把 " 和 r 开头的词当作字符串 所以不会解析出字节字符串
+        Ok(LiteralValue::Bytes(_)) => Err(ScriptError {
+            line,
+            column: token.column,
+            kind: ScriptErrorKind::ExpectedString(token.text.to_string()),
+        }),
+        Err(err) => Err(ScriptError {
+            line,
+            column: token.column + token.text[..err.offset.min(token.text.len())].chars().count(),
+            kind: ScriptErrorKind::InvalidString(err),
+        }),
+    }
+}
+
+// 返回每条命令和它所在的行、列 用来在转换失败时报告位置
+fn parse_commands(text: &str) -> Result<Vec<(usize, usize, Command)>, ScriptError> {
+    let mut commands = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let tokens = tokenize(line);
+        let Some((name, args)) = tokens.split_first() else {
+            continue;
+        };
+        let (command, expected): (&'static str, usize) = match name.text {
+            "say" if !name.quoted => ("say", 1),
+            "move" if !name.quoted => ("move", 2),
+            "color" if !name.quoted => ("color", 3),
+            "quit" if !name.quoted => ("quit", 0),
+            other => {
+                return Err(ScriptError {
+                    line: line_no,
+                    column: name.column,
+                    kind: ScriptErrorKind::UnknownCommand(other.to_string()),
+                })
+            }
+        };
+        if args.len() != expected {
+            // 多了参数时指向第一个多余的参数 少了参数时指向命令本身
+            let column = args.get(expected).map_or(name.column, |t| t.column);
+            return Err(ScriptError {
+                line: line_no,
+                column,
+                kind: ScriptErrorKind::ArgumentCount { command, expected, found: args.len() },
+            });
+        }
+        let n = |i: usize| number(&args[i], line_no);
+        let parsed = match command {
+            "say" => Command::Say(string(&args[0], line_no)?),
+            "move" => Command::Move(n(0)?, n(1)?),
+            "color" => Command::Color(n(0)?, n(1)?, n(2)?),
+            _ => Command::Quit,
+        };
+        commands.push((line_no, name.column, parsed));
+    }
+    Ok(commands)
+}
+
+pub fn parse_actions(text: &str) -> Result<Vec<Action>, ScriptError> {
+    parse_commands(text)?
+        .into_iter()
+        .map(|(line, column, command)| match command {
+            Command::Say(s) => Ok(Action::Say(s)),
+            Command::Move(x, y) => Ok(Action::MoveTo(x, y)),
+            Command::Color(r, g, b) => Ok(Action::ChangeColor(r, g, b)),
+            Command::Quit => {
+                Err(ScriptError { line, column, kind: ScriptErrorKind::Unsupported { command: command.name(), target: "Action" } })
+            }
+        })
+        .collect()
+}
+
+pub fn parse_messages(text: &str) -> Result<Vec<Message>, ScriptError> {
+    Ok(parse_commands(text)?
+        .into_iter()
+        .map(|(_, _, command)| match command {
+            Command::Say(s) => Message::Write(s),
+            Command::Move(x, y) => Message::Move { x, y },
+            Command::Color(r, g, b) => Message::ChangeColor(r, g, b),
+            Command::Quit => Message::Quit,
+        })
+        .collect())
+}
+
+fn format_command(command: &Command) -> String {
+    match command {
+        // 脚本按行解析 包含换行的字符串不能写成原始字符串 只能转义
+        Command::Say(s) if s.contains('\n') => format!("say {}", escape_str(s)),
+        Command::Say(s) => format!("say {}", to_literal(s)),
+        Command::Move(x, y) => format!("move {} {}", x, y),
+        Command::Color(r, g, b) => format!("color {} {} {}", r, g, b),
+        Command::Quit => String::from("quit"),
+    }
+}
+
+// 反过来把Action写成脚本 parse_actions(&to_script(a)) == a
+pub fn actions_to_script(actions: &[Action]) -> String {
+    let mut out = String::new();
+    for action in actions {
+        let command = match action {
+            Action::Say(s) => Command::Say(s.clone()),
+            Action::MoveTo(x, y) => Command::Move(*x, *y),
+            Action::ChangeColor(r, g, b) => Command::Color(*r, *g, *b),
+        };
+        out.push_str(&format_command(&command));
+        out.push('\n');
+    }
+    out
+}
+
+// myrust run-script square.script 在画布上执行脚本并打印ASCII字符画 出错时返回false
+pub fn run_file<P: AsRef<Path>>(path: P, width: usize, height: usize) -> bool {
+    let path = path.as_ref();
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            return false;
+        }
+    };
+    let actions = match parse_actions(&text) {
+        Ok(actions) => actions,
+        Err(err) => {
+            // 和编译器一样用 文件:行:列 的格式 编辑器可以直接跳转
+            eprintln!("{}:{}", path.display(), err);
+            return false;
+        }
+    };
+    let mut canvas = Canvas::new(width, height);
+    let result = canvas.run(&actions);
+    print!("{}", canvas.to_ascii());
+    for ((x, y), s) in canvas.speech() {
+        println!("({}, {}) {}", x, y, s);
+    }
+    if let Err(err) = result {
+        eprintln!("{}: {}", path.display(), err);
+        return false;
+    }
+    true
+}
+
+pub fn demo() {
+    let script = include_str!("../scripts/square.script");
+    match parse_actions(script) {
+        Ok(actions) => {
+            println!("{:?}", actions);
+            let mut canvas = Canvas::new(5, 5);
+            if let Err(err) = canvas.run(&actions) {
+                println!("{}", err);
+            }
+            print!("{}", canvas.to_ascii());
+            // 写回脚本 格式和手写的不一定相同 但解析后得到同样的actions
+            print!("{}", actions_to_script(&actions));
+        }
+        Err(err) => println!("{}", err),
+    }
+
+    match parse_messages("say \"a \\\"quoted\\\" #word\" # 注释\n\nmove -1 2\ncolor 1 2 3\nquit\n") {
+        Ok(messages) => println!("{:?}", messages),
+        Err(err) => println!("{}", err),
+    }
+
+    // 错误报告 行号:列号 列号按字符计算 中文也只算一列
+    for text in ["jump 1 2", "\n  move 1", "say \"你好\" 世界", "move 0 0\nquit", "say \"好\\q\""] {
+        if let Err(err) = parse_actions(text) {
+            println!("{}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_square_script() {
+        let actions = parse_actions(include_str!("../scripts/square.script")).unwrap();
+        assert_eq!(actions.len(), 7);
+        assert_eq!(actions[0], Action::Say(String::from("画一个正方形")));
+        assert_eq!(actions[1], Action::ChangeColor(0, 200, 0));
+        let mut canvas = Canvas::new(5, 5);
+        canvas.run(&actions).unwrap();
+        assert_eq!(canvas.to_ascii(), "+++++\n+...+\n+...+\n+...+\n+++++\n");
+    }
+
+    // 和 main.rs 中写死的actions相同
+    #[test]
+    fn parses_lesson_actions() {
+        let parsed = parse_actions("say \"hello\"\nmove 1 2\ncolor 255 255 0\n").unwrap();
+        assert_eq!(parsed, [Action::Say(String::from("hello")), Action::MoveTo(1, 2), Action::ChangeColor(255, 255, 0)]);
+    }
+
+    #[test]
+    fn round_trips_through_script() {
+        let actions = parse_actions(include_str!("../scripts/square.script")).unwrap();
+        assert_eq!(parse_actions(&actions_to_script(&actions)).unwrap(), actions);
+        let tricky = [Action::Say(String::from("a \"b\" #c\\d\n")), Action::MoveTo(i32::MIN, i32::MAX)];
+        assert_eq!(parse_actions(&actions_to_script(&tricky)).unwrap(), tricky);
+    }
+
+    #[test]
+    fn parses_messages_and_strings() {
+        let messages = parse_messages("say \"a \\\"quoted\\\" #word\" # 注释\n\nmove -1 2\ncolor 1 2 3\nquit\n").unwrap();
+        assert_eq!(
+            messages,
+            [
+                Message::Write(String::from("a \"quoted\" #word")),
+                Message::Move { x: -1, y: 2 },
+                Message::ChangeColor(1, 2, 3),
+                Message::Quit
+            ]
+        );
+        assert_eq!(parse_actions(r##"say r#"C:\"path""#"##).unwrap(), [Action::Say(String::from(r#"C:\"path""#))]);
+        // Message有quit
+        assert_eq!(parse_messages("quit").unwrap(), [Message::Quit]);
+    }
+
+    // 错误报告 行号:列号 列号按字符计算 中文也只算一列
+    #[test]
+    fn reports_error_positions() {
+        let cases = [
+            ("jump 1 2", 1, 1, ScriptErrorKind::UnknownCommand(String::from("jump"))),
+            ("\n  move 1", 2, 3, ScriptErrorKind::ArgumentCount { command: "move", expected: 2, found: 1 }),
+            ("color 1 2 3 4", 1, 13, ScriptErrorKind::ArgumentCount { command: "color", expected: 3, found: 4 }),
+            ("move 1 x2", 1, 8, ScriptErrorKind::ExpectedNumber(String::from("x2"))),
+            ("move 1 99999999999", 1, 8, ScriptErrorKind::ExpectedNumber(String::from("99999999999"))),
+            ("say hello", 1, 5, ScriptErrorKind::ExpectedString(String::from("hello"))),
+            ("move \"1\" 2", 1, 6, ScriptErrorKind::ExpectedNumber(String::from("\"1\""))),
+            ("say 你好", 1, 5, ScriptErrorKind::ExpectedString(String::from("你好"))),
+            ("say \"你好\" 世界", 1, 10, ScriptErrorKind::ArgumentCount { command: "say", expected: 1, found: 2 }),
+            ("move 0 0\nquit", 2, 1, ScriptErrorKind::Unsupported { command: "quit", target: "Action" }),
+        ];
+        for (text, line, column, kind) in cases {
+            assert_eq!(parse_actions(text), Err(ScriptError { line, column, kind }), "{:?}", text);
+        }
+        // 字符串内部的错误指向具体的字符
+        let err = parse_actions("say \"好\\q\"").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 8));
+        assert!(matches!(err.kind, ScriptErrorKind::InvalidString(_)));
+        assert!(matches!(parse_actions("say \"abc").unwrap_err().kind, ScriptErrorKind::InvalidString(_)));
+    }
+}
@@ -0,0 +1,272 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// 智能指针 在 Box<dyn Draw> 的基础上继续
+// Box<T>          唯一所有权 值放在堆上
+// Rc<T>           单线程的共享所有权 用引用计数记录有多少个所有者 计数归零时释放
+// RefCell<T>      把借用检查推迟到运行时 让共享的值也能被修改(内部可变性)
+// Weak<T>         不增加强引用计数的指针 用来打破Rc的循环引用 使用前需要upgrade
+// Arc<Mutex<T>>   Rc<RefCell<T>> 的多线程版本 计数是原子操作 修改前需要加锁
+
+// 当前线程中还活着的Button和Node的数量 用来检查是否发生了内存泄漏
+// 每个线程单独计数 这样并行运行的测试不会互相影响 Button和Node都在创建它们的线程中被drop
+thread_local! {
+    static LIVE_BUTTONS: Cell<usize> = const { Cell::new(0) };
+    static LIVE_NODES: Cell<usize> = const { Cell::new(0) };
+}
+
+fn live_buttons() -> usize {
+    LIVE_BUTTONS.with(Cell::get)
+}
+
+fn live_nodes() -> usize {
+    LIVE_NODES.with(Cell::get)
+}
+
+pub trait Draw {
+    fn draw(&self) -> String;
+}
+
+#[derive(Debug)]
+pub struct Button {
+    pub label: String,
+    pub clicks: u32,
+}
+
+impl Button {
+    pub fn new(label: &str) -> Button {
+        LIVE_BUTTONS.with(|n| n.set(n.get() + 1));
+        Button { label: label.to_string(), clicks: 0 }
+    }
+
+    pub fn click(&mut self) {
+        self.clicks += 1;
+    }
+}
+
+impl Draw for Button {
+    fn draw(&self) -> String {
+        format!("[{} x{}]", self.label, self.clicks)
+    }
+}
+
+impl Drop for Button {
+    fn drop(&mut self) {
+        LIVE_BUTTONS.with(|n| n.set(n.get() - 1));
+    }
+}
+
+// 和 main.rs 中的Screen一样保存一组组件 但组件是共享的 同一个Button可以出现在多个Screen上
+pub struct Screen {
+    pub name: String,
+    pub components: Vec<Rc<RefCell<dyn Draw>>>,
+}
+
+impl Screen {
+    pub fn run(&self) -> String {
+        let parts: Vec<String> = self.components.iter().map(|c| c.borrow().draw()).collect();
+        format!("{}: {}", self.name, parts.join(" "))
+    }
+}
+
+fn shared_button() {
+    let ok = Rc::new(RefCell::new(Button::new("OK")));
+    println!("创建后 strong_count = {}", Rc::strong_count(&ok));
+    {
+        // Rc::clone只是把计数加一 不会深拷贝Button
+        // Rc<RefCell<Button>> 可以直接转换成 Rc<RefCell<dyn Draw>>
+        let main_screen = Screen { name: String::from("main"), components: vec![ok.clone()] };
+        let dialog = Screen { name: String::from("dialog"), components: vec![ok.clone(), ok.clone()] };
+        println!("放进两个Screen后 strong_count = {}", Rc::strong_count(&ok));
+        // 通过任何一个所有者修改 所有Screen都能看到
+        ok.borrow_mut().click();
+        println!("{}\n{}", main_screen.run(), dialog.run());
+    }
+    // Screen被drop后 它们持有的计数也被减掉了
+    println!("Screen离开作用域后 strong_count = {}", Rc::strong_count(&ok));
+}
+
+// 树 父节点通过Rc拥有子节点 子节点通过Weak指回父节点
+// 如果子节点也用Rc指向父节点 父子之间的计数永远不会归零 就会内存泄漏
+#[derive(Debug)]
+pub struct Node {
+    pub value: i32,
+    pub parent: RefCell<Weak<Node>>,
+    pub children: RefCell<Vec<Rc<Node>>>,
+}
+
+impl Node {
+    pub fn new(value: i32) -> Rc<Node> {
+        LIVE_NODES.with(|n| n.set(n.get() + 1));
+        Rc::new(Node { value, parent: RefCell::new(Weak::new()), children: RefCell::new(Vec::new()) })
+    }
+
+    pub fn add_child(parent: &Rc<Node>, child: Rc<Node>) {
+        *child.parent.borrow_mut() = Rc::downgrade(parent);
+        parent.children.borrow_mut().push(child);
+    }
+
+    // 从当前节点一直走到根节点 返回经过的值
+    pub fn path_to_root(&self) -> Vec<i32> {
+        let mut path = vec![self.value];
+        let mut parent = self.parent.borrow().upgrade();
+        while let Some(node) = parent {
+            path.push(node.value);
+            parent = node.parent.borrow().upgrade();
+        }
+        path
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        LIVE_NODES.with(|n| n.set(n.get() - 1));
+    }
+}
+
+fn weak_tree() {
+    let leaf = Node::new(3);
+    println!("leaf strong = {}, weak = {}", Rc::strong_count(&leaf), Rc::weak_count(&leaf));
+    {
+        let root = Node::new(1);
+        let branch = Node::new(2);
+        Node::add_child(&root, branch.clone());
+        Node::add_child(&branch, leaf.clone());
+        println!("leaf到根节点的路径 {:?}", leaf.path_to_root());
+        // branch被root和变量branch拥有 被leaf弱引用
+        println!("branch strong = {}, weak = {}", Rc::strong_count(&branch), Rc::weak_count(&branch));
+    }
+    // root和branch已经被释放 弱引用upgrade失败 只剩下leaf
+    println!("root被释放后leaf的路径 {:?}", leaf.path_to_root());
+}
+
+// 反面例子 两个节点用Rc互相指向 离开作用域后谁都没有被释放
+struct Leaky {
+    _node: Rc<Node>,
+    other: RefCell<Option<Rc<Leaky>>>,
+}
+
+// 创建a和b互相指向的循环 只返回a的弱引用 函数返回后已经没有变量能访问它们了
+fn make_cycle() -> Weak<Leaky> {
+    let a = Rc::new(Leaky { _node: Node::new(10), other: RefCell::new(None) });
+    let b = Rc::new(Leaky { _node: Node::new(20), other: RefCell::new(Some(a.clone())) });
+    *a.other.borrow_mut() = Some(b);
+    Rc::downgrade(&a)
+}
+
+fn rc_cycle() {
+    let a = make_cycle();
+    // 没有变量持有a 但b还指向它 所以强引用计数停在1 弱引用仍然能upgrade
+    println!("循环引用没有打破时 a的strong_count = {} 泄漏的节点数 = {}", a.strong_count(), live_nodes());
+    // 从弱引用拿到a 手动打破循环 两个节点才会被释放
+    if let Some(a) = a.upgrade() {
+        a.other.borrow_mut().take();
+    }
+    println!("打破循环后 a能否upgrade = {} 泄漏的节点数 = {}", a.upgrade().is_some(), live_nodes());
+}
+
+// 多线程版本 Rc没有实现Send 不能在线程间传递 需要换成Arc 同理RefCell换成Mutex
+// 启动threads个线程 每个线程点击n次 返回总的点击次数
+fn arc_mutex(threads: usize, n: u32) -> u32 {
+    let ok = Arc::new(Mutex::new(Button::new("OK")));
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let ok = Arc::clone(&ok);
+            thread::spawn(move || {
+                for _ in 0..n {
+                    // lock返回的守卫离开作用域时自动解锁
+                    if let Ok(mut button) = ok.lock() {
+                        button.click();
+                    }
+                }
+            })
+        })
+        .collect();
+    // 每个线程持有一份clone 已经结束的线程会把自己的计数减掉
+    println!("线程运行时 Arc strong_count = {}", Arc::strong_count(&ok));
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    // 线程结束后它们持有的Arc也被drop了
+    println!("线程结束后 Arc strong_count = {}", Arc::strong_count(&ok));
+    ok.lock().map(|b| b.clicks).unwrap_or_default()
+}
+
+pub fn demo() {
+    shared_button();
+    weak_tree();
+    rc_cycle();
+    println!("4个线程各点击100次 clicks = {}", arc_mutex(4, 100));
+    println!("演示结束后还活着的Button数量 = {}", live_buttons());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rc_shares_one_button() {
+        let ok = Rc::new(RefCell::new(Button::new("OK")));
+        {
+            let main_screen = Screen { name: String::from("main"), components: vec![ok.clone()] };
+            let dialog = Screen { name: String::from("dialog"), components: vec![ok.clone(), ok.clone()] };
+            assert_eq!(Rc::strong_count(&ok), 4);
+            ok.borrow_mut().click();
+            assert_eq!(main_screen.run(), "main: [OK x1]");
+            assert_eq!(dialog.run(), "dialog: [OK x1] [OK x1]");
+        }
+        assert_eq!(Rc::strong_count(&ok), 1);
+        assert_eq!(live_buttons(), 1);
+        drop(ok);
+        assert_eq!(live_buttons(), 0);
+    }
+
+    #[test]
+    fn weak_parent_does_not_keep_tree_alive() {
+        let leaf = Node::new(3);
+        {
+            let root = Node::new(1);
+            let branch = Node::new(2);
+            Node::add_child(&root, branch.clone());
+            Node::add_child(&branch, leaf.clone());
+            assert_eq!(leaf.path_to_root(), [3, 2, 1]);
+            assert_eq!((Rc::strong_count(&branch), Rc::weak_count(&branch)), (2, 1));
+            assert_eq!((Rc::strong_count(&leaf), Rc::weak_count(&leaf)), (2, 0));
+            assert_eq!(live_nodes(), 3);
+        }
+        assert!(leaf.parent.borrow().upgrade().is_none());
+        assert_eq!(leaf.path_to_root(), [3]);
+        assert_eq!(Rc::strong_count(&leaf), 1);
+        assert_eq!(live_nodes(), 1);
+        drop(leaf);
+        assert_eq!(live_nodes(), 0);
+    }
+
+    #[test]
+    fn rc_cycle_leaks_until_broken() {
+        let a = make_cycle();
+        // 没有任何变量持有a和b 但它们都还活着
+        assert_eq!(a.strong_count(), 1);
+        let upgraded = a.upgrade().expect("循环中的a应该还活着");
+        let b = upgraded.other.borrow().clone().expect("a应该指向b");
+        assert_eq!((Rc::strong_count(&upgraded), Rc::strong_count(&b)), (2, 2));
+        assert!(b.other.borrow().as_ref().is_some_and(|other| Rc::ptr_eq(other, &upgraded)));
+        assert_eq!(live_nodes(), 2);
+        drop((upgraded, b));
+
+        // 打破循环后两个节点都被释放
+        if let Some(a) = a.upgrade() {
+            a.other.borrow_mut().take();
+        }
+        assert!(a.upgrade().is_none());
+        assert_eq!(live_nodes(), 0);
+    }
+
+    #[test]
+    fn arc_mutex_counts_every_click() {
+        assert_eq!(arc_mutex(4, 100), 400);
+        assert_eq!(live_buttons(), 0);
+    }
+}
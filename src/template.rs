@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::unicode::str_width;
+
+// 运行时的模板引擎 占位符语法和 format! 一致: {name} {name:>10} {value:.2} {name:?} {{ }}
+// format! 的格式串必须在编译期确定 而这里的模板可以在运行时读取 所以参数不存在或者格式写错时需要返回错误 而不是编译失败
+// 和 std::fmt 不同的一点: 宽度按终端显示宽度计算 一个汉字算两列 这样中英文混排时也能对齐
+// 占位符名字为空时({} 或 {:?}) 和 format! 一样按出现的顺序依次使用 "0" "1" "2"... 作为名字
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Str(_) => "字符串",
+            Value::Int(_) => "整数",
+            Value::Float(_) => "浮点数",
+            Value::Bool(_) => "布尔值",
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::Str(s)
+    }
+}
+
+impl From<&String> for Value {
+    fn from(s: &String) -> Value {
+        Value::Str(s.clone())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Value {
+        Value::Int(i)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Value {
+        Value::Int(i.into())
+    }
+}
+
+impl From<u8> for Value {
+    fn from(i: u8) -> Value {
+        Value::Int(i.into())
+    }
+}
+
+impl From<usize> for Value {
+    fn from(i: usize) -> Value {
+        // 超过i64范围的值在课程中不会出现 这里直接截断到i64::MAX
+        Value::Int(i64::try_from(i).unwrap_or(i64::MAX))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Value {
+        Value::Float(f)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TemplateErrorKind {
+    // 有 { 但没有对应的 }
+    Unclosed,
+    // 单独出现的 } 应该写成 }}
+    UnmatchedClose,
+    InvalidName(String),
+    InvalidSpec(String),
+    UnknownKey(String),
+    // 格式类型不适用于这个值 例如 {name:x} 但name是字符串
+    UnsupportedType { ty: char, kind: &'static str },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TemplateError {
+    pub kind: TemplateErrorKind,
+    // 出错的占位符在模板中的字节偏移
+    pub offset: usize,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TemplateErrorKind::Unclosed => write!(f, "偏移{}处的 {{ 没有闭合", self.offset),
+            TemplateErrorKind::UnmatchedClose => write!(f, "偏移{}处有多余的 }} 需要写成 }}}}", self.offset),
+            TemplateErrorKind::InvalidName(name) => write!(f, "偏移{}处的参数名 {:?} 不合法", self.offset, name),
+            TemplateErrorKind::InvalidSpec(reason) => write!(f, "偏移{}处的格式说明不合法: {}", self.offset, reason),
+            TemplateErrorKind::UnknownKey(key) => write!(f, "偏移{}处的参数 {} 不存在", self.offset, key),
+            TemplateErrorKind::UnsupportedType { ty, kind } => {
+                write!(f, "偏移{}处的格式类型 {} 不能用于{}", self.offset, ty, kind)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+    // None 表示没有指定类型 即 Display
+    ty: Option<char>,
+}
+
+#[derive(Debug)]
+enum Piece {
+    Text(String),
+    Placeholder { name: String, spec: Spec, offset: usize },
+}
+
+#[derive(Debug)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+// [[fill]align][+]['#']['0'][width]['.' precision][type]
+fn parse_spec(s: &str) -> Result<Spec, String> {
+    let mut spec = Spec::default();
+    let chars: Vec<char> = s.chars().collect();
+    let mut i = 0;
+    let align_of = |c: char| match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None,
+    };
+    if let Some(align) = chars.get(1).and_then(|&c| align_of(c)) {
+        spec.fill = Some(chars[0]);
+        spec.align = Some(align);
+        i = 2;
+    } else if let Some(align) = chars.first().and_then(|&c| align_of(c)) {
+        spec.align = Some(align);
+        i = 1;
+    }
+    if chars.get(i) == Some(&'+') {
+        spec.plus = true;
+        i += 1;
+    }
+    if chars.get(i) == Some(&'#') {
+        spec.alternate = true;
+        i += 1;
+    }
+    if chars.get(i) == Some(&'0') {
+        spec.zero = true;
+        i += 1;
+    }
+    let digits = |i: &mut usize| -> Option<usize> {
+        let start = *i;
+        while chars.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+            *i += 1;
+        }
+        chars[start..*i].iter().collect::<String>().parse().ok()
+    };
+    spec.width = digits(&mut i);
+    if chars.get(i) == Some(&'.') {
+        i += 1;
+        spec.precision = Some(digits(&mut i).ok_or("'.' 后面必须是精度数字")?);
+    }
+    match &chars[i..] {
+        [] => {}
+        [ty @ ('?' | 'x' | 'X' | 'o' | 'b' | 'e' | 'E')] => spec.ty = Some(*ty),
+        rest => return Err(format!("无法识别 {:?}", rest.iter().collect::<String>())),
+    }
+    Ok(spec)
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        None => true,
+        Some(c) if c.is_ascii_digit() => name.chars().all(|c| c.is_ascii_digit()),
+        // 和Rust的标识符规则一样 允许汉字等unicode字母
+        Some(c) => (c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_'),
+    }
+}
+
+impl Template {
+    pub fn parse(src: &str) -> Result<Template, TemplateError> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut next_index = 0;
+        let mut chars = src.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|&(_, c)| c) == Some('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek().map(|&(_, c)| c) == Some('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(TemplateError { kind: TemplateErrorKind::UnmatchedClose, offset: i }),
+                '{' => {
+                    let err = |kind| TemplateError { kind, offset: i };
+                    let len = src[i..].find('}').ok_or(err(TemplateErrorKind::Unclosed))?;
+                    let inner = &src[i + 1..i + len];
+                    let (name, spec) = match inner.split_once(':') {
+                        Some((name, spec)) => (name.trim(), spec),
+                        None => (inner.trim(), ""),
+                    };
+                    if !is_valid_name(name) {
+                        return Err(err(TemplateErrorKind::InvalidName(name.to_string())));
+                    }
+                    let spec = parse_spec(spec).map_err(|reason| err(TemplateErrorKind::InvalidSpec(reason)))?;
+                    let name = if name.is_empty() {
+                        next_index += 1;
+                        (next_index - 1).to_string()
+                    } else {
+                        name.to_string()
+                    };
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Placeholder { name, spec, offset: i });
+                    // 跳过占位符剩下的部分
+                    while chars.next_if(|&(j, _)| j <= i + len).is_some() {}
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Template { pieces })
+    }
+
+    pub fn render(&self, values: &HashMap<&str, Value>) -> Result<String, TemplateError> {
+        let mut out = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Placeholder { name, spec, offset } => {
+                    let value = values.get(name.as_str()).ok_or(TemplateError {
+                        kind: TemplateErrorKind::UnknownKey(name.clone()),
+                        offset: *offset,
+                    })?;
+                    let formatted = format_value(value, spec)
+                        .map_err(|kind| TemplateError { kind, offset: *offset })?;
+                    out.push_str(&formatted);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn format_value(value: &Value, spec: &Spec) -> Result<String, TemplateErrorKind> {
+    let unsupported = |ty| TemplateErrorKind::UnsupportedType { ty, kind: value.kind() };
+    // 先格式化出不带填充的内容 sign_len 是开头的符号和0x等前缀的长度 用0填充时要填在它们后面
+    let (body, sign_len) = match (value, spec.ty) {
+        (Value::Str(s), None) => match spec.precision {
+            // 字符串的精度表示最多保留几个字符
+            Some(p) => (s.chars().take(p).collect(), 0),
+            None => (s.clone(), 0),
+        },
+        (Value::Str(s), Some('?')) => (format!("{:?}", s), 0),
+        (Value::Bool(b), None | Some('?')) => (b.to_string(), 0),
+        (Value::Int(i), ty) => {
+            let sign = if *i < 0 { "-" } else if spec.plus { "+" } else { "" };
+            let n = i.unsigned_abs();
+            let (prefix, digits) = match ty {
+                None | Some('?') => ("", n.to_string()),
+                Some('x') => ("0x", format!("{:x}", n)),
+                Some('X') => ("0x", format!("{:X}", n)),
+                Some('o') => ("0o", format!("{:o}", n)),
+                Some('b') => ("0b", format!("{:b}", n)),
+                Some('e') => ("", format!("{:e}", n)),
+                Some('E') => ("", format!("{:E}", n)),
+                Some(ty) => return Err(unsupported(ty)),
+            };
+            let prefix = if spec.alternate { prefix } else { "" };
+            (format!("{}{}{}", sign, prefix, digits), sign.len() + prefix.len())
+        }
+        (Value::Float(f), ty) => {
+            let body = match (ty, spec.precision) {
+                (None, Some(p)) => format!("{:.*}", p, f),
+                (None, None) => format!("{}", f),
+                (Some('?'), Some(p)) => format!("{:.*?}", p, f),
+                (Some('?'), None) => format!("{:?}", f),
+                (Some('e'), Some(p)) => format!("{:.*e}", p, f),
+                (Some('e'), None) => format!("{:e}", f),
+                (Some('E'), Some(p)) => format!("{:.*E}", p, f),
+                (Some('E'), None) => format!("{:E}", f),
+                (Some(ty), _) => return Err(unsupported(ty)),
+            };
+            let sign = usize::from(body.starts_with('-'));
+            if spec.plus && sign == 0 {
+                (format!("+{}", body), 1)
+            } else {
+                (body, sign)
+            }
+        }
+        (_, Some(ty)) => return Err(unsupported(ty)),
+    };
+
+    let width = spec.width.unwrap_or(0);
+    let fill = width.saturating_sub(str_width(&body));
+    if fill == 0 {
+        return Ok(body);
+    }
+    let is_number = matches!(value, Value::Int(_) | Value::Float(_));
+    // 数字的0填充会忽略对齐方式 0填在符号后面: -0042
+    if spec.zero && is_number {
+        let (sign, digits) = body.split_at(sign_len);
+        return Ok(format!("{}{}{}", sign, "0".repeat(fill), digits));
+    }
+    // 默认对齐方式和format!一致: 数字右对齐 其它左对齐
+    let align = spec.align.unwrap_or(if is_number { Align::Right } else { Align::Left });
+    let (left, right) = match align {
+        Align::Left => (0, fill),
+        Align::Right => (fill, 0),
+        Align::Center => (fill / 2, fill - fill / 2),
+    };
+    let pad = spec.fill.unwrap_or(' ').to_string();
+    Ok(format!("{}{}{}", pad.repeat(left), body, pad.repeat(right)))
+}
+
+// 解析并渲染 模板只用一次时使用
+pub fn render(src: &str, values: &HashMap<&str, Value>) -> Result<String, TemplateError> {
+    Template::parse(src)?.render(values)
+}
+
+// 课程中打印输出用 和 println! 一样在末尾换行 参数按(名字, 值)依次给出
+// 模板写错时打印错误信息而不是panic
+pub fn print_line(src: &str, values: &[(&str, Value)]) {
+    let values: HashMap<&str, Value> = values.iter().cloned().collect();
+    match render(src, &values) {
+        Ok(line) => println!("{}", line),
+        Err(e) => println!("模板 {:?} 渲染失败: {}", src, e),
+    }
+}
+
+pub fn demo() {
+    let s1 = String::from("hello ");
+    let s2 = String::from("world");
+    // 和 format!("{}{}{}", s1, s2, "!") 的效果一样 但模板是运行时的字符串
+    print_line("s3 = {}{}{}", &[("0", Value::from(s1)), ("1", Value::from(s2)), ("2", Value::from("!"))]);
+
+    let values = [
+        ("name", Value::from("张三")),
+        ("pi", Value::from(std::f64::consts::PI)),
+        ("n", Value::from(-42)),
+        ("ok", Value::from(true)),
+    ];
+    // 负数的十六进制 format!会输出补码 0xffffffffffffffd6 这里输出带符号的形式
+    print_line("{n} 的十六进制是 {n:#x} 圆周率约等于 {pi:.2}", &values);
+    // 按显示宽度对齐: "张三" 占4列 所以只补6个空格 format!("{:>10}") 会补8个
+    print_line("[{name:>10}] {ok}", &values);
+
+    // 课程中的中英文输出
+    let rows = [("字符", "char", 4), ("字节", "u8", 1), ("字符串", "String", 24)];
+    if let Ok(row) = Template::parse("|{zh:<8}|{en:^8}|{size:>4}|") {
+        for (zh, en, size) in rows {
+            let values = HashMap::from([("zh", Value::from(zh)), ("en", Value::from(en)), ("size", Value::from(size))]);
+            match row.render(&values) {
+                Ok(line) => println!("{}", line),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+
+    // 错误会指出具体位置
+    let values: HashMap<&str, Value> = values.into_iter().collect();
+    for bad in ["{name", "a}b", "{age}", "{pi:.}", "{name:x}", "{pi:>>>}", "{na me}"] {
+        if let Err(e) = render(bad, &values) {
+            println!("{} => {}", bad, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lesson_values() -> HashMap<&'static str, Value> {
+        HashMap::from([
+            ("name", Value::from("张三")),
+            ("pi", Value::from(std::f64::consts::PI)),
+            ("n", Value::from(-42)),
+            ("ok", Value::from(true)),
+        ])
+    }
+
+    #[test]
+    fn positional_placeholders() {
+        let values = HashMap::from([("0", Value::from("hello ")), ("1", Value::from("world")), ("2", Value::from("!"))]);
+        assert_eq!(render("{}{}{}", &values).as_deref(), Ok("hello world!"));
+    }
+
+    #[test]
+    fn matches_format_macro() {
+        let mut values = lesson_values();
+        values.insert("0", Value::from(6.4));
+        let cases = [
+            ("{name:?}", format!("{:?}", "张三")),
+            ("{pi:.2}", format!("{:.2}", std::f64::consts::PI)),
+            ("{pi:+.3e}", format!("{:+.3e}", std::f64::consts::PI)),
+            ("{n:05}", format!("{:05}", -42)),
+            ("{n:*^9}", format!("{:*^9}", -42)),
+            ("{ok:>6}|{{}}", format!("{:>6}|{{}}", true)),
+            ("{}", format!("{}", 6.4)),
+        ];
+        for (src, expected) in &cases {
+            assert_eq!(render(src, &values).as_deref(), Ok(expected.as_str()), "{}", src);
+        }
+    }
+
+    #[test]
+    fn signed_hex_and_display_width() {
+        let values = lesson_values();
+        assert_eq!(render("{n:#x}", &values).as_deref(), Ok("-0x2a"));
+        assert_eq!(render("[{name:>10}]", &values).as_deref(), Ok("[      张三]"));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let values = lesson_values();
+        for bad in ["{name", "a}b", "{age}", "{pi:.}", "{name:x}", "{pi:>>>}", "{na me}"] {
+            assert!(render(bad, &values).is_err(), "{}", bad);
+        }
+        assert_eq!(
+            render("{age}", &values).unwrap_err(),
+            TemplateError { kind: TemplateErrorKind::UnknownKey(String::from("age")), offset: 0 }
+        );
+    }
+}
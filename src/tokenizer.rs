@@ -0,0 +1,237 @@
+use std::ops::Range;
+
+// 分词器 把一段文本切分成单词 返回的都是对原字符串的借用(&str) 而不是新的String
+// 所以只要分词结果还在使用 原字符串就不能被修改 这正是 main.rs 里 s.clear() 报错的原因
+
+// 中日韩文字之间没有空格 用一个很小的词典做正向最大匹配 匹配不上的按单个字切分
+const DEFAULT_DICTIONARY: &[&str] = &[
+    "你好", "世界", "中文", "汉字", "字符", "字符串", "字节", "切片", "所有权", "借用", "引用", "变量",
+    "函数", "结构体", "枚举", "特征", "泛型", "生命周期", "张三", "李四", "王五",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharClass {
+    Space,
+    Punct,
+    Cjk,
+    Word,
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'     // 平假名 片假名
+        | '\u{3400}'..='\u{4DBF}'   // 扩展A
+        | '\u{4E00}'..='\u{9FFF}'   // 基本汉字
+        | '\u{F900}'..='\u{FAFF}'   // 兼容汉字
+        | '\u{20000}'..='\u{2FA1F}' // 扩展B及以后
+    )
+}
+
+fn is_punct(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(c,
+            '\u{2010}'..='\u{205E}'   // 通用标点 —— … “ ” 等
+            | '\u{3000}'..='\u{303F}' // 中日韩标点 。、「」 等
+            | '\u{FF01}'..='\u{FF0F}' // 全角标点 ！＂＃ 等
+            | '\u{FF1A}'..='\u{FF20}'
+            | '\u{FF3B}'..='\u{FF40}'
+            | '\u{FF5B}'..='\u{FF65}'
+        )
+}
+
+fn classify(c: char) -> CharClass {
+    // 全角空格U+3000 同时也在中日韩标点区间里 先判断空白
+    if c.is_whitespace() {
+        CharClass::Space
+    } else if is_cjk(c) {
+        CharClass::Cjk
+    } else if is_punct(c) {
+        CharClass::Punct
+    } else {
+        CharClass::Word
+    }
+}
+
+pub struct Tokenizer<'d> {
+    dictionary: &'d [&'d str],
+    // 词典中最长词的字符数 匹配时最多向后看这么多个字
+    max_word_chars: usize,
+}
+
+impl Tokenizer<'static> {
+    pub fn new() -> Tokenizer<'static> {
+        Tokenizer::with_dictionary(DEFAULT_DICTIONARY)
+    }
+}
+
+impl<'d> Tokenizer<'d> {
+    pub fn with_dictionary(dictionary: &'d [&'d str]) -> Tokenizer<'d> {
+        // 至少要能取到一个字符 否则词典只有空字符串时 match_cjk 一个候选都没有
+        let max_word_chars = dictionary.iter().map(|w| w.chars().count()).max().unwrap_or(1).max(1);
+        Tokenizer { dictionary, max_word_chars }
+    }
+
+    // 每个单词在原字符串中的字节范围
+    pub fn word_spans<'a>(&'a self, s: &'a str) -> WordSpans<'a> {
+        WordSpans { tokenizer: self, text: s, pos: 0 }
+    }
+
+    pub fn words<'a>(&'a self, s: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.word_spans(s).map(move |r| &s[r])
+    }
+
+    pub fn nth_word<'a>(&self, s: &'a str, n: usize) -> Option<&'a str> {
+        self.word_spans(s).nth(n).map(|r| &s[r])
+    }
+
+    pub fn first_word<'a>(&self, s: &'a str) -> Option<&'a str> {
+        self.nth_word(s, 0)
+    }
+
+    // 在一段连续的中日韩文字开头 找词典中能匹配上的最长词 返回它的字节长度
+    fn match_cjk(&self, run: &str) -> usize {
+        let ends: Vec<usize> = run
+            .char_indices()
+            .skip(1)
+            .map(|(i, _)| i)
+            .chain(std::iter::once(run.len()))
+            .take(self.max_word_chars)
+            .collect();
+        ends.iter()
+            .rev()
+            .find(|&&end| self.dictionary.contains(&&run[..end]))
+            .copied()
+            // 词典里没有 就单独成词
+            .unwrap_or(ends[0])
+    }
+}
+
+impl Default for Tokenizer<'static> {
+    fn default() -> Self {
+        Tokenizer::new()
+    }
+}
+
+pub struct WordSpans<'a> {
+    tokenizer: &'a Tokenizer<'a>,
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Iterator for WordSpans<'a> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        let rest = &self.text[self.pos..];
+        // 跳过空白和标点
+        let (offset, first) = rest
+            .char_indices()
+            .find(|&(_, c)| matches!(classify(c), CharClass::Word | CharClass::Cjk))?;
+        let start = self.pos + offset;
+        let rest = &self.text[start..];
+
+        let len = if classify(first) == CharClass::Cjk {
+            let run_end = rest
+                .char_indices()
+                .find(|&(_, c)| classify(c) != CharClass::Cjk)
+                .map_or(rest.len(), |(i, _)| i);
+            self.tokenizer.match_cjk(&rest[..run_end])
+        } else {
+            let mut end = rest.len();
+            let mut chars = rest.char_indices().peekable();
+            while let Some((i, c)) = chars.next() {
+                match classify(c) {
+                    CharClass::Word => continue,
+                    // 单词中间的撇号和连字符属于单词本身 例如 don't、well-known
+                    CharClass::Punct if c == '\'' || c == '-' || c == '’' => {
+                        if let Some(&(_, next)) = chars.peek() {
+                            if classify(next) == CharClass::Word {
+                                continue;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                end = i;
+                break;
+            }
+            end
+        };
+
+        self.pos = start + len;
+        Some(start..self.pos)
+    }
+}
+
+// 以下是使用默认词典的便捷函数
+pub fn word_spans(s: &str) -> Vec<Range<usize>> {
+    Tokenizer::new().word_spans(s).collect()
+}
+
+pub fn words(s: &str) -> Vec<&str> {
+    word_spans(s).into_iter().map(|r| &s[r]).collect()
+}
+
+pub fn nth_word(s: &str, n: usize) -> Option<&str> {
+    Tokenizer::new().nth_word(s, n)
+}
+
+// 没有单词时返回空字符串 方便直接打印
+pub fn first_word(s: &str) -> &str {
+    Tokenizer::new().first_word(s).unwrap_or("")
+}
+
+pub fn demo() {
+    for text in ["hello world", "  \t\u{3000}Rust!", "Hello, world! don't well-known -x", "你好世界，我爱字符串", "Rust的所有权和借用"] {
+        println!("{:?} => first_word = {:?} words = {:?}", text, first_word(text), words(text));
+    }
+
+    println!("第2个单词 = {:?}", nth_word("zsyu9779 发表了 微博", 1));
+
+    let text = "张三说：“hello 世界”";
+    for span in word_spans(text) {
+        println!("{:?} => {}", span.clone(), &text[span]);
+    }
+
+    // 自定义词典
+    let dict = ["发表了"];
+    let tokenizer = Tokenizer::with_dictionary(&dict);
+    println!("words = {:?}", tokenizer.words("张三发表了微博").collect::<Vec<_>>());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_word_skips_whitespace_and_punctuation() {
+        assert_eq!(first_word("hello world"), "hello");
+        assert_eq!(first_word("  \t\u{3000}Rust!"), "Rust");
+        assert_eq!(first_word("。，"), "");
+    }
+
+    #[test]
+    fn splits_latin_words() {
+        assert_eq!(words("Hello, world! don't well-known -x"), ["Hello", "world", "don't", "well-known", "x"]);
+        assert_eq!(nth_word("one two", 5), None);
+    }
+
+    #[test]
+    fn splits_cjk_by_dictionary() {
+        assert_eq!(words("你好世界，我爱字符串"), ["你好", "世界", "我", "爱", "字符串"]);
+        assert_eq!(words("Rust的所有权和借用"), ["Rust", "的", "所有权", "和", "借用"]);
+        assert_eq!(nth_word("zsyu9779 发表了 微博", 1), Some("发"));
+        let dict = ["发表了"];
+        let tokenizer = Tokenizer::with_dictionary(&dict);
+        assert_eq!(tokenizer.words("张三发表了微博").collect::<Vec<_>>(), ["张", "三", "发表了", "微", "博"]);
+    }
+
+    #[test]
+    fn empty_words_in_dictionary() {
+        for dict in [&[""][..], &["", "你好"][..], &[][..]] {
+            let tokenizer = Tokenizer::with_dictionary(dict);
+            let expected: &[&str] = if dict.contains(&"你好") { &["你好", "世"] } else { &["你", "好", "世"] };
+            assert_eq!(tokenizer.words("你好世").collect::<Vec<_>>(), expected);
+        }
+    }
+}
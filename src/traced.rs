@@ -0,0 +1,236 @@
+use std::cell::RefCell;
+use std::fmt;
+
+// 所有权追踪 Traced<T> 在创建、克隆、移动和drop时打印一条记录 把注释中描述的所有权转移变成实际的时间线
+// 编译器不会在值被移动时通知我们 所以"移动进函数"需要调用 move_into 显式声明
+// 作用域用 Scope 表示 进入函数时创建 离开函数时自动销毁 这样drop记录就能知道自己发生在哪个作用域的结尾
+
+thread_local! {
+    // 当前的作用域栈 栈顶是正在执行的函数或代码块
+    static SCOPES: RefCell<Vec<String>> = RefCell::new(vec![String::from("main")]);
+    // 按发生顺序记录的事件 方便在演示结束后检查
+    static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn depth() -> usize {
+    SCOPES.with(|s| s.borrow().len())
+}
+
+fn current_scope() -> String {
+    SCOPES.with(|s| s.borrow().last().cloned().unwrap_or_default())
+}
+
+// 调用当前函数的作用域 函数返回值会被移动到这里
+fn caller_scope() -> String {
+    SCOPES.with(|s| {
+        let scopes = s.borrow();
+        scopes.iter().rev().nth(1).cloned().unwrap_or_default()
+    })
+}
+
+fn record(event: String) {
+    println!("{}{}", "  ".repeat(depth().saturating_sub(1)), event);
+    EVENTS.with(|e| e.borrow_mut().push(event));
+}
+
+// 取出并清空到目前为止的事件记录
+pub fn take_events() -> Vec<String> {
+    EVENTS.with(|e| std::mem::take(&mut *e.borrow_mut()))
+}
+
+// 作用域守卫 创建时进入作用域 离开时(drop)退出作用域
+// 注意要写成 let _scope = ... 如果写成 let _ = ... 守卫会立刻被drop
+pub struct Scope {
+    name: String,
+}
+
+impl Scope {
+    pub fn enter(name: &str) -> Scope {
+        SCOPES.with(|s| s.borrow_mut().push(name.to_string()));
+        record(format!("进入 {}", name));
+        Scope { name: name.to_string() }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        // 守卫是在函数开头创建的 局部变量按声明的相反顺序drop 所以它在函数体内所有局部变量之后才被drop
+        // 但函数的参数比函数体内的局部变量drop得更晚 所以参数的drop记录会出现在"离开"之后
+        record(format!("离开 {}", self.name));
+        SCOPES.with(|s| s.borrow_mut().pop());
+    }
+}
+
+pub struct Traced<T> {
+    name: String,
+    // 值当前所在的作用域和它的深度 drop时用来说明是在哪里被释放的
+    // 深度不能在drop时再读取: 函数参数在函数的Scope守卫退出之后才被drop 那时栈顶已经是调用方了
+    owner: String,
+    owner_depth: usize,
+    value: T,
+}
+
+impl<T: fmt::Debug> Traced<T> {
+    pub fn new(name: &str, value: T) -> Traced<T> {
+        let owner = current_scope();
+        record(format!("{} = {:?} 在 {} 中创建", name, value, owner));
+        Traced { name: name.to_string(), owner, owner_depth: depth(), value }
+    }
+
+    // 把值移动进一个函数 调用方之后不能再使用它 和 takes_ownership(s) 对应
+    pub fn move_into(mut self, function: &str, param: &str) -> Traced<T> {
+        record(format!("{} 被移动进 {} (参数 {})", self.name, function, param));
+        self.owner = function.to_string();
+        // 被调用的函数会在当前作用域之上再进入一层
+        self.owner_depth = depth() + 1;
+        self.name = param.to_string();
+        self
+    }
+
+    // 把值从当前函数返回给调用方 调用方把它绑定到 binding 上
+    pub fn return_to(mut self, binding: &str) -> Traced<T> {
+        let caller = caller_scope();
+        record(format!("{} 从 {} 返回 移动给 {} 中的 {}", self.name, self.owner, caller, binding));
+        self.owner = caller;
+        self.owner_depth = depth() - 1;
+        self.name = binding.to_string();
+        self
+    }
+
+    // let s2 = s1; 同一个作用域内的移动
+    pub fn rename(mut self, binding: &str) -> Traced<T> {
+        record(format!("{} 被移动给 {} 此后 {} 失效", self.name, binding, self.name));
+        self.name = binding.to_string();
+        self
+    }
+
+    pub fn clone_as(&self, binding: &str) -> Traced<T>
+    where
+        T: Clone,
+    {
+        record(format!("{} = {}.clone() 深拷贝了一份数据", binding, self.name));
+        Traced { name: binding.to_string(), owner: current_scope(), owner_depth: depth(), value: self.value.clone() }
+    }
+
+    // Copy类型传给函数时复制一份 原来的变量仍然有效 和 makes_copy(x) 对应
+    pub fn copy_into(&self, function: &str, param: &str) -> Traced<T>
+    where
+        T: Copy,
+    {
+        record(format!("{} 被复制进 {} (参数 {}) {} 仍然有效", self.name, function, param, self.name));
+        Traced { name: param.to_string(), owner: function.to_string(), owner_depth: depth() + 1, value: self.value }
+    }
+
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+// 通过Clone特征克隆时不知道新变量的名字 用 原名.clone 表示
+impl<T: Clone + fmt::Debug> Clone for Traced<T> {
+    fn clone(&self) -> Traced<T> {
+        self.clone_as(&format!("{}.clone", self.name))
+    }
+}
+
+impl<T> Drop for Traced<T> {
+    fn drop(&mut self) {
+        record(format!("{} 在 {} 结束时被drop (作用域深度{})", self.name, self.owner, self.owner_depth));
+    }
+}
+
+// 以下四个函数和 main.rs 中的同名函数一一对应
+fn takes_ownership(some_string: Traced<String>) {
+    let _scope = Scope::enter("takes_ownership");
+    println!("{}", some_string.get());
+}
+
+fn makes_copy(some_integer: Traced<i32>) {
+    let _scope = Scope::enter("makes_copy");
+    println!("{}", some_integer.get());
+}
+
+fn gives_ownership() -> Traced<String> {
+    let _scope = Scope::enter("gives_ownership");
+    let some_string = Traced::new("some_string", String::from("hello"));
+    some_string.return_to("s1")
+}
+
+fn takes_and_gives_back(a_string: Traced<String>) -> Traced<String> {
+    let _scope = Scope::enter("takes_and_gives_back");
+    a_string.return_to("s3")
+}
+
+fn ownership_lesson() {
+    let _scope = Scope::enter("ownership_lesson");
+    let s = Traced::new("s", String::from("hello"));
+    takes_ownership(s.move_into("takes_ownership", "some_string"));
+    let x = Traced::new("x", 5);
+    makes_copy(x.copy_into("makes_copy", "some_integer"));
+    println!("x = {}", x.get());
+
+    let s1 = gives_ownership();
+    let s2 = Traced::new("s2", String::from("hello"));
+    let mut s3 = takes_and_gives_back(s2.move_into("takes_and_gives_back", "a_string"));
+    s3.get_mut().push_str(", world");
+    let s4 = s3.clone_as("s4");
+    let s5 = s1.rename("s5");
+    println!("s3 = {}, s4 = {}, s5 = {}", s3.get(), s4.get(), s5.get());
+    let _s6 = s5.clone();
+}
+
+pub fn demo() {
+    take_events();
+    ownership_lesson();
+    println!("共记录了{}个所有权事件", take_events().len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lesson_events() -> Vec<String> {
+        take_events();
+        ownership_lesson();
+        take_events()
+    }
+
+    // s 在 takes_ownership 结束时就被释放了 而不是在 main 中
+    #[test]
+    fn moved_value_drops_at_end_of_callee() {
+        let events = lesson_events();
+        let moved = events.iter().position(|e| e == "s 被移动进 takes_ownership (参数 some_string)");
+        let dropped = events.iter().position(|e| e.starts_with("some_string 在 takes_ownership 结束时被drop"));
+        let left = events.iter().position(|e| e == "离开 takes_ownership");
+        assert!(moved.is_some() && moved < left && left < dropped);
+    }
+
+    // 参数在Scope守卫退出之后才drop 但记录的仍然是函数自己的深度 main是1 ownership_lesson是2
+    #[test]
+    fn drop_reports_owner_depth() {
+        let events = lesson_events();
+        for expected in [
+            "some_string 在 takes_ownership 结束时被drop (作用域深度3)",
+            "some_integer 在 makes_copy 结束时被drop (作用域深度3)",
+            "s5 在 ownership_lesson 结束时被drop (作用域深度2)",
+            "x 在 ownership_lesson 结束时被drop (作用域深度2)",
+        ] {
+            assert!(events.iter().any(|e| e == expected), "{}\n{:#?}", expected, events);
+        }
+    }
+
+    // 局部变量按声明的相反顺序drop
+    #[test]
+    fn locals_drop_in_reverse_order() {
+        let drops: Vec<String> = lesson_events()
+            .iter()
+            .filter(|e| e.contains("ownership_lesson 结束时被drop"))
+            .map(|e| e.split(' ').next().unwrap_or_default().to_string())
+            .collect();
+        assert_eq!(drops, ["s5.clone", "s5", "s4", "s3", "x"]);
+    }
+}
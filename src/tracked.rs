@@ -0,0 +1,299 @@
+use std::cell::{Ref, RefCell, RefMut};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+// 运行时的借用检查模拟 Tracked<T> 和 RefCell 一样在运行时记录借用
+// 区别是每个借用都带有一个标签(比如 r1) 发生冲突时返回一个同时指出两处借用的错误 而不是panic
+// 规则和编译期的借用检查一致: 同一时刻要么只有一个可变借用 要么有任意多个不可变借用
+// 和编译器不同的是 这里借用的结束是守卫被drop的时候 而不是最后一次使用的时候(NLL)
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BorrowError {
+    // 被借用的变量名
+    pub name: String,
+    // 这次借用的标签和是否可变
+    pub label: String,
+    pub mutable: bool,
+    // 与之冲突的那个仍然存活的借用
+    pub existing: String,
+    pub existing_mutable: bool,
+}
+
+impl BorrowError {
+    // 两个可变借用冲突是E0499 可变和不可变借用冲突是E0502
+    pub fn code(&self) -> &'static str {
+        if self.mutable && self.existing_mutable {
+            "E0499"
+        } else {
+            "E0502"
+        }
+    }
+}
+
+fn describe(mutable: bool) -> &'static str {
+    if mutable {
+        "可变借用"
+    } else {
+        "不可变借用"
+    }
+}
+
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mutable && self.existing_mutable {
+            write!(
+                f,
+                "error[E0499]: 不能在 {} 处第二次可变借用 {} 第一次可变借用 {} 仍然存活",
+                self.label, self.name, self.existing
+            )
+        } else {
+            write!(
+                f,
+                "error[E0502]: 不能在 {} 处{} {} 因为它已经在 {} 处被{}",
+                self.label,
+                describe(self.mutable),
+                self.name,
+                self.existing,
+                describe(self.existing_mutable)
+            )
+        }
+    }
+}
+
+impl std::error::Error for BorrowError {}
+
+#[derive(Debug)]
+struct Borrow {
+    id: usize,
+    label: String,
+    mutable: bool,
+}
+
+#[derive(Debug, Default)]
+struct BorrowState {
+    live: Vec<Borrow>,
+    next_id: usize,
+}
+
+impl BorrowState {
+    fn acquire(&mut self, label: &str, mutable: bool) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.live.push(Borrow { id, label: label.to_string(), mutable });
+        id
+    }
+
+    fn release(&mut self, id: usize) {
+        self.live.retain(|b| b.id != id);
+    }
+}
+
+#[derive(Debug)]
+pub struct Tracked<T> {
+    name: String,
+    value: RefCell<T>,
+    state: RefCell<BorrowState>,
+}
+
+impl<T> Tracked<T> {
+    pub fn new(name: &str, value: T) -> Tracked<T> {
+        Tracked { name: name.to_string(), value: RefCell::new(value), state: RefCell::new(BorrowState::default()) }
+    }
+
+    fn conflict(&self, label: &str, mutable: bool) -> Option<BorrowError> {
+        let state = self.state.borrow();
+        // 不可变借用只和可变借用冲突 可变借用和任何借用都冲突
+        let existing = state.live.iter().find(|b| mutable || b.mutable)?;
+        Some(BorrowError {
+            name: self.name.clone(),
+            label: label.to_string(),
+            mutable,
+            existing: existing.label.clone(),
+            existing_mutable: existing.mutable,
+        })
+    }
+
+    // 对应 let label = &value;
+    pub fn borrow(&self, label: &str) -> Result<TrackedRef<'_, T>, BorrowError> {
+        if let Some(err) = self.conflict(label, false) {
+            return Err(err);
+        }
+        let id = self.state.borrow_mut().acquire(label, false);
+        Ok(TrackedRef { value: self.value.borrow(), state: &self.state, id })
+    }
+
+    // 对应 let label = &mut value;
+    pub fn borrow_mut(&self, label: &str) -> Result<TrackedRefMut<'_, T>, BorrowError> {
+        if let Some(err) = self.conflict(label, true) {
+            return Err(err);
+        }
+        let id = self.state.borrow_mut().acquire(label, true);
+        Ok(TrackedRefMut { value: self.value.borrow_mut(), state: &self.state, id })
+    }
+
+    // 当前存活的借用 按创建顺序排列 可变借用前面加上 &mut
+    pub fn live_borrows(&self) -> Vec<String> {
+        self.state
+            .borrow()
+            .live
+            .iter()
+            .map(|b| if b.mutable { format!("&mut {}", b.label) } else { format!("&{}", b.label) })
+            .collect()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+// 借用守卫 drop时借用结束 相当于引用的最后一次使用
+pub struct TrackedRef<'a, T> {
+    value: Ref<'a, T>,
+    state: &'a RefCell<BorrowState>,
+    id: usize,
+}
+
+impl<T> Deref for TrackedRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> Drop for TrackedRef<'_, T> {
+    fn drop(&mut self) {
+        self.state.borrow_mut().release(self.id);
+    }
+}
+
+pub struct TrackedRefMut<'a, T> {
+    value: RefMut<'a, T>,
+    state: &'a RefCell<BorrowState>,
+    id: usize,
+}
+
+impl<T> Deref for TrackedRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for TrackedRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> Drop for TrackedRefMut<'_, T> {
+    fn drop(&mut self) {
+        self.state.borrow_mut().release(self.id);
+    }
+}
+
+fn print_result<T>(result: Result<T, BorrowError>) -> Option<T> {
+    match result {
+        Ok(guard) => Some(guard),
+        Err(err) => {
+            println!("{} ({})", err, err.code());
+            None
+        }
+    }
+}
+
+pub fn demo() {
+    // 对应 main.rs 中的 let r1 = &mut s; let r2 = &mut s; 如果r1在r2借用时还活着 就是E0499
+    let s = Tracked::new("s", String::from("hello"));
+    if let Some(r1) = print_result(s.borrow_mut("r1")) {
+        print_result(s.borrow_mut("r2"));
+        println!("r1 = {}", *r1);
+        // 在r1之后不再使用它 编译器会认为借用已经结束 这里需要手动drop守卫
+    }
+    if let Some(mut r2) = print_result(s.borrow_mut("r2")) {
+        r2.push_str(", world");
+    }
+
+    // 对应 { let _r1 = &mut s; } 守卫离开作用域时借用就结束了
+    {
+        let _r1 = print_result(s.borrow_mut("_r1"));
+        println!("作用域内存活的借用: {:?}", s.live_borrows());
+    }
+    println!("离开作用域后存活的借用: {:?}", s.live_borrows());
+
+    // 可变引用和不可变引用不能同时存在 对应被注释掉的 let r3 = &mut s; 是E0502
+    let r1 = print_result(s.borrow("r1"));
+    let r2 = print_result(s.borrow("r2"));
+    print_result(s.borrow_mut("r3"));
+    println!("存活的借用: {:?}", s.live_borrows());
+    drop((r1, r2));
+
+    // 反过来 在可变借用存活时进行不可变借用 同样是E0502
+    let r3 = print_result(s.borrow_mut("r3"));
+    print_result(s.borrow("r4"));
+    drop(r3);
+
+    println!("s = {}", s.into_inner());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_borrows_coexist() {
+        let s = Tracked::new("s", String::from("hello"));
+        let r1 = s.borrow("r1").unwrap();
+        let r2 = s.borrow("r2").unwrap();
+        assert_eq!(*r1, *r2);
+        assert_eq!(s.live_borrows(), ["&r1", "&r2"]);
+    }
+
+    #[test]
+    fn mut_then_shared_is_e0502() {
+        let s = Tracked::new("s", String::from("hello"));
+        let _r3 = s.borrow_mut("r3").unwrap();
+        let err = s.borrow("r4").err().unwrap();
+        assert_eq!((err.code(), err.mutable, err.existing_mutable), ("E0502", false, true));
+        assert_eq!(err.existing, "r3");
+
+        // 反过来 不可变借用存活时的可变借用也是E0502 错误指出的是最早的那个借用
+        let t = Tracked::new("t", 0);
+        let r1 = t.borrow("r1").unwrap();
+        let r2 = t.borrow("r2").unwrap();
+        let err = t.borrow_mut("r3").err().unwrap();
+        assert_eq!((err.code(), err.existing.as_str()), ("E0502", "r1"));
+        drop(r1);
+        // 只要还有一个不可变借用活着 可变借用就会失败 错误中指出的是剩下的那个
+        assert_eq!(t.borrow_mut("r3").err().map(|e| e.existing), Some(String::from("r2")));
+        drop(r2);
+    }
+
+    #[test]
+    fn mut_then_mut_is_e0499() {
+        let s = Tracked::new("s", String::from("hello"));
+        let _r1 = s.borrow_mut("r1").unwrap();
+        let err = s.borrow_mut("r2").err().unwrap();
+        assert_eq!(err.code(), "E0499");
+        assert_eq!((err.label.as_str(), err.existing.as_str()), ("r2", "r1"));
+        assert!(err.to_string().starts_with("error[E0499]"));
+    }
+
+    #[test]
+    fn release_then_borrow() {
+        let s = Tracked::new("s", String::from("hello"));
+        let r1 = s.borrow_mut("r1").unwrap();
+        drop(r1);
+        let mut r2 = s.borrow_mut("r2").unwrap();
+        r2.push_str(", world");
+        drop(r2);
+        {
+            let _r1 = s.borrow_mut("_r1").unwrap();
+            assert_eq!(s.live_borrows(), ["&mut _r1"]);
+        }
+        assert!(s.live_borrows().is_empty());
+        assert!(s.borrow("r3").is_ok());
+        assert_eq!(s.into_inner(), "hello, world");
+    }
+}
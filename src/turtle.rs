@@ -0,0 +1,438 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::history::Inverse;
+use crate::Action;
+
+// 执行一串Action的解释器 像海龟绘图一样: 画布上有一支笔 记录位置、颜色和说过的话
+//   MoveTo(x, y): 用当前颜色从笔的位置画直线到(x, y) 然后把笔移过去
+//   ChangeColor(r, g, b): 换颜色 每个分量必须在0..=255之间
+//   Say(s): 把话记在笔当前的位置
+// 坐标原点在左上角 x向右 y向下 画到画布外面的点会被忽略 笔本身可以移到画布外面
+// 画好的图可以导出成PPM图片或者ASCII字符画
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+
+    // 分量超出范围时报告第一个不合法的分量
+    pub fn new(r: i32, g: i32, b: i32) -> Result<Rgb, CanvasError> {
+        let channel = |channel: &'static str, value: i32| {
+            u8::try_from(value).map_err(|_| CanvasError::ColorOutOfRange { channel, value })
+        };
+        Ok(Rgb(channel("r", r)?, channel("g", g)?, channel("b", b)?))
+    }
+
+    // ITU-R BT.601的亮度 用来决定ASCII字符画中用哪个字符
+    fn luma(self) -> u32 {
+        (299 * self.0 as u32 + 587 * self.1 as u32 + 114 * self.2 as u32) / 1000
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanvasError {
+    ColorOutOfRange { channel: &'static str, value: i32 },
+}
+
+impl fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CanvasError::ColorOutOfRange { channel, value } => {
+                write!(f, "颜色分量{}的值{}超出了0..=255", channel, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CanvasError {}
+
+// 第index个Action执行失败 之前的Action已经生效
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunError {
+    pub index: usize,
+    pub error: CanvasError,
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第{}个动作执行失败: {}", self.index + 1, self.error)
+    }
+}
+
+impl std::error::Error for RunError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    // 按行存储 None表示还没画过的背景
+    pixels: Vec<Option<Rgb>>,
+    pen: (i32, i32),
+    color: Rgb,
+    speech: Vec<((i32, i32), String)>,
+}
+
+impl Canvas {
+    // 笔从(0, 0)开始 颜色为黑色
+    pub fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: vec![None; width * height],
+            pen: (0, 0),
+            color: Rgb::BLACK,
+            speech: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pen(&self) -> (i32, i32) {
+        self.pen
+    }
+
+    pub fn color(&self) -> Rgb {
+        self.color
+    }
+
+    pub fn speech(&self) -> &[((i32, i32), String)] {
+        &self.speech
+    }
+
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+        Some(y as usize * self.width + x as usize)
+    }
+
+    // 画布外面和没画过的点都返回None
+    pub fn pixel(&self, x: i32, y: i32) -> Option<Rgb> {
+        self.index(x as i64, y as i64).and_then(|i| self.pixels[i])
+    }
+
+    // Bresenham直线算法 只用整数运算 对直线经过的每个画布内的点调用f
+    // 变化较大的坐标叫主方向 每一步主方向走1格 第k步时次方向走了 floor((2 * 次方向差 * k + 主方向差) / (2 * 主方向差)) 格
+    // 这和逐步累加误差项得到的点完全相同 所以可以先像Liang–Barsky那样按参数k裁剪: 求出落在画布内的k的范围 只遍历这一段
+    // 不管端点离画布多远 遍历的点数都不超过画布的宽或高 端点在i32两端时中间的乘积会超出i64 所以用i128计算
+    fn for_each_line_point(&self, from: (i32, i32), to: (i32, i32), mut f: impl FnMut(usize)) {
+        let (x0, y0, x1, y1) = (from.0 as i128, from.1 as i128, to.0 as i128, to.1 as i128);
+        let (w, h) = (self.width as i128, self.height as i128);
+        let x_major = (x1 - x0).abs() >= (y1 - y0).abs();
+        // (起点, 差值, 画布在这个方向上的长度) 先主方向后次方向
+        let ((m0, dm, m_len), (n0, dn, n_len)) =
+            if x_major { ((x0, x1 - x0, w), (y0, y1 - y0, h)) } else { ((y0, y1 - y0, h), (x0, x1 - x0, w)) };
+        let (major, minor) = (dm.abs(), dn.abs());
+        // 主方向上第k步的坐标是 m0 + k * signum(dm) 在画布内的k的范围
+        let Some((mut lo, mut hi)) = steps_inside(m0, dm.signum(), m_len) else {
+            return;
+        };
+        hi = hi.min(major);
+        lo = lo.max(0);
+        // 次方向走过的格数在画布内的范围 再换算成k的范围
+        let Some((n_lo, n_hi)) = steps_inside(n0, dn.signum(), n_len) else {
+            return;
+        };
+        if minor > 0 {
+            lo = lo.max(ceil_div(2 * major * n_lo - major, 2 * minor));
+            hi = hi.min((2 * major * n_hi + major - 1).div_euclid(2 * minor));
+        }
+        for k in lo..=hi {
+            let offset = if major == 0 { 0 } else { (2 * minor * k + major).div_euclid(2 * major) };
+            let (m, n) = (m0 + dm.signum() * k, n0 + dn.signum() * offset);
+            let (x, y) = if x_major { (m, n) } else { (n, m) };
+            f(y as usize * self.width + x as usize);
+        }
+    }
+
+    // 画直线 返回被覆盖的像素在画布中的下标和原来的值 撤销时用它们恢复
+    fn line(&mut self, from: (i32, i32), to: (i32, i32)) -> Vec<(usize, Option<Rgb>)> {
+        let mut points = Vec::new();
+        self.for_each_line_point(from, to, |i| points.push(i));
+        points.into_iter().map(|i| (i, self.pixels[i].replace(self.color))).collect()
+    }
+
+    pub fn execute(&mut self, action: &Action) -> Result<(), CanvasError> {
+        self.execute_undoable(action).map(|_| ())
+    }
+
+    // 执行动作并返回它的逆操作 交给revert就能撤销这个动作 见 history.rs
+    // 画线时顺便记下被覆盖的像素 不需要在执行之前把同一条线再走一遍
+    pub fn execute_undoable(&mut self, action: &Action) -> Result<Inverse, CanvasError> {
+        let inverse = match action {
+            Action::Say(s) => {
+                self.speech.push((self.pen, s.clone()));
+                Inverse::Unsay
+            }
+            Action::MoveTo(x, y) => {
+                let pen = self.pen;
+                let pixels = self.line(pen, (*x, *y));
+                self.pen = (*x, *y);
+                Inverse::MoveBack { pen, pixels }
+            }
+            Action::ChangeColor(r, g, b) => Inverse::RestoreColor(std::mem::replace(&mut self.color, Rgb::new(*r, *g, *b)?)),
+        };
+        Ok(inverse)
+    }
+
+    pub fn revert(&mut self, inverse: Inverse) {
+        match inverse {
+            Inverse::Unsay => {
+                self.speech.pop();
+            }
+            Inverse::MoveBack { pen, pixels } => {
+                self.pen = pen;
+                for (i, pixel) in pixels {
+                    self.pixels[i] = pixel;
+                }
+            }
+            Inverse::RestoreColor(color) => self.color = color,
+        }
+    }
+
+    // 依次执行 遇到第一个错误就停下
+    pub fn run(&mut self, actions: &[Action]) -> Result<(), RunError> {
+        for (index, action) in actions.iter().enumerate() {
+            self.execute(action).map_err(|error| RunError { index, error })?;
+        }
+        Ok(())
+    }
+
+    // 二进制的PPM(P6)格式: 文本头 "P6 宽 高 255" 之后每个像素3个字节 背景是白色
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut out = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        for pixel in &self.pixels {
+            let Rgb(r, g, b) = pixel.unwrap_or(Rgb::WHITE);
+            out.extend_from_slice(&[r, g, b]);
+        }
+        out
+    }
+
+    pub fn save_ppm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_ppm())
+    }
+
+    // 每个像素一个字符 背景是. 画过的点按亮度从暗到亮用 # + - 表示
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            for pixel in row {
+                out.push(match pixel {
+                    None => '.',
+                    Some(color) if color.luma() < 85 => '#',
+                    Some(color) if color.luma() < 170 => '+',
+                    Some(_) => '-',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+// 坐标 start + step * k 落在 0..len 内的k的范围 step为0时要么所有k都可以 要么都不行
+fn steps_inside(start: i128, step: i128, len: i128) -> Option<(i128, i128)> {
+    match step {
+        _ if len == 0 => None,
+        0 if (0..len).contains(&start) => Some((i128::MIN / 4, i128::MAX / 4)),
+        0 => None,
+        1 => Some((-start, len - 1 - start)),
+        _ => Some((start - (len - 1), start)),
+    }
+}
+
+fn ceil_div(a: i128, b: i128) -> i128 {
+    -(-a).div_euclid(b)
+}
+
+fn lesson_actions() -> Vec<Action> {
+    vec![
+        Action::Say(String::from("开始")),
+        Action::MoveTo(7, 0),
+        Action::ChangeColor(255, 0, 0),
+        Action::MoveTo(7, 4),
+        Action::ChangeColor(255, 255, 0),
+        Action::MoveTo(0, 0),
+        Action::Say(String::from("回到原点")),
+        // 画布外面的部分被裁掉
+        Action::ChangeColor(0, 0, 255),
+        Action::MoveTo(-5, 4),
+    ]
+}
+
+pub fn demo() {
+    let mut canvas = Canvas::new(8, 5);
+    if let Err(err) = canvas.run(&lesson_actions()) {
+        println!("{}", err);
+    }
+    println!("{}x{}的画布:", canvas.width(), canvas.height());
+    print!("{}", canvas.to_ascii());
+    println!("笔在{:?} 颜色{:?} 说过的话{:?}", canvas.pen(), canvas.color(), canvas.speech());
+    println!("(7, 2) = {:?} (9, 0) = {:?}", canvas.pixel(7, 2), canvas.pixel(9, 0));
+
+    // 临时目录不可写时只打印错误
+    let path = std::env::temp_dir().join(format!("myrust_turtle_{}.ppm", std::process::id()));
+    match canvas.save_ppm(&path) {
+        Ok(()) => {
+            println!("已保存PPM图片 {}字节", canvas.to_ppm().len());
+            let _ = fs::remove_file(&path);
+        }
+        Err(err) => println!("保存 {} 失败: {}", path.display(), err),
+    }
+
+    // 颜色分量超出范围 出错之前的动作已经生效
+    let mut canvas = Canvas::new(4, 1);
+    if let Err(err) = canvas.run(&[Action::MoveTo(3, 0), Action::ChangeColor(0, 256, -1), Action::Say(String::from("不会执行"))]) {
+        println!("{}", err);
+    }
+    print!("{}", canvas.to_ascii());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+    use std::time::{Duration, Instant};
+
+    // 逐点累加误差项的Bresenham 作为裁剪版本的参照 只用于端点离画布不远的直线
+    fn reference_points(canvas: &Canvas, from: (i32, i32), to: (i32, i32)) -> Vec<usize> {
+        let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+        let (x1, y1) = (to.0 as i64, to.1 as i64);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = (if x < x1 { 1 } else { -1 }, if y < y1 { 1 } else { -1 });
+        let mut err = dx + dy;
+        let mut points = Vec::new();
+        loop {
+            points.extend(canvas.index(x, y));
+            if x == x1 && y == y1 {
+                return points;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    fn clipped_points(canvas: &Canvas, from: (i32, i32), to: (i32, i32)) -> Vec<usize> {
+        let mut points = Vec::new();
+        canvas.for_each_line_point(from, to, |i| points.push(i));
+        points
+    }
+
+    #[test]
+    fn draws_lesson_actions() {
+        let mut canvas = Canvas::new(8, 5);
+        assert_eq!((canvas.width(), canvas.height()), (8, 5));
+        canvas.run(&lesson_actions()).unwrap();
+        assert_eq!(canvas.to_ascii(), "########\n.--....#\n...--..#\n.....--#\n.......-\n");
+        assert_eq!(canvas.pen(), (-5, 4));
+        assert_eq!(canvas.color(), Rgb(0, 0, 255));
+        assert_eq!(canvas.speech(), [((0, 0), String::from("开始")), ((0, 0), String::from("回到原点"))]);
+        assert_eq!(canvas.pixel(7, 2), Some(Rgb(255, 0, 0)));
+        // 原点最后被蓝色的线覆盖
+        assert_eq!(canvas.pixel(0, 0), Some(Rgb(0, 0, 255)));
+        assert_eq!((canvas.pixel(3, 3), canvas.pixel(9, 0)), (None, None));
+    }
+
+    #[test]
+    fn exports_ppm() {
+        let mut canvas = Canvas::new(8, 5);
+        canvas.run(&lesson_actions()).unwrap();
+        let ppm = canvas.to_ppm();
+        let header = "P6\n8 5\n255\n";
+        assert!(ppm.starts_with(header.as_bytes()));
+        assert_eq!(ppm.len(), header.len() + 8 * 5 * 3);
+        // 第二行第一个像素是背景 白色
+        let offset = header.len() + 8 * 3;
+        assert_eq!(ppm[offset..offset + 3], [255, 255, 255]);
+
+        let path = std::env::temp_dir().join(format!("myrust_turtle_test_{}.ppm", std::process::id()));
+        canvas.save_ppm(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), ppm);
+        let _ = fs::remove_file(&path);
+    }
+
+    // 颜色分量超出范围 出错之前的动作已经生效
+    #[test]
+    fn stops_at_invalid_color() {
+        let mut canvas = Canvas::new(4, 1);
+        let err = canvas.run(&[Action::MoveTo(3, 0), Action::ChangeColor(0, 256, -1), Action::Say(String::from("不会执行"))]);
+        assert_eq!(err, Err(RunError { index: 1, error: CanvasError::ColorOutOfRange { channel: "g", value: 256 } }));
+        assert_eq!(canvas.to_ascii(), "####\n");
+        assert!(canvas.speech().is_empty());
+        assert_eq!(Rgb::new(0, 0, -1), Err(CanvasError::ColorOutOfRange { channel: "b", value: -1 }));
+    }
+
+    #[test]
+    fn clipping_matches_stepping() {
+        let mut rng = XorShift::new(48);
+        for _ in 0..20_000 {
+            let canvas = Canvas::new(rng.below(7), rng.below(7));
+            let mut point = || (rng.below(31) as i32 - 12, rng.below(31) as i32 - 12);
+            let (from, to) = (point(), point());
+            assert_eq!(clipped_points(&canvas, from, to), reference_points(&canvas, from, to), "{:?} -> {:?}", from, to);
+        }
+    }
+
+    #[test]
+    fn far_lines_are_clipped() {
+        let start = Instant::now();
+        let mut canvas = Canvas::new(3, 3);
+        let actions = [
+            Action::MoveTo(i32::MIN, i32::MIN),
+            Action::MoveTo(i32::MAX, i32::MIN),
+            Action::MoveTo(-1, -1),
+            Action::MoveTo(3, 3),
+        ];
+        canvas.run(&actions).unwrap();
+        assert_eq!(canvas.to_ascii(), "#..\n.#.\n..#\n");
+
+        // 穿过画布的长线只画画布内的点 y从1变成2正好发生在中点x = 0处
+        let mut far = Canvas::new(8, 4);
+        far.run(&[Action::MoveTo(-2_000_000_000, 1), Action::MoveTo(2_000_000_000, 2)]).unwrap();
+        assert_eq!(far.to_ascii(), "#.......\n........\n########\n........\n");
+        let mut diagonal = Canvas::new(4, 4);
+        diagonal.run(&[Action::MoveTo(i32::MIN + 1, i32::MIN + 1), Action::MoveTo(i32::MAX, i32::MAX)]).unwrap();
+        assert_eq!(diagonal.to_ascii(), "#...\n.#..\n..#.\n...#\n");
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn undo_restores_overwritten_pixels() {
+        let mut canvas = Canvas::new(4, 1);
+        canvas.execute(&Action::MoveTo(1, 0)).unwrap();
+        canvas.execute(&Action::ChangeColor(255, 255, 255)).unwrap();
+        let before = canvas.clone();
+        let inverse = canvas.execute_undoable(&Action::MoveTo(3, 0)).unwrap();
+        assert_eq!(canvas.to_ascii(), "#---\n");
+        assert_eq!(inverse, Inverse::MoveBack { pen: (1, 0), pixels: vec![(1, Some(Rgb::BLACK)), (2, None), (3, None)] });
+        canvas.revert(inverse);
+        assert_eq!(canvas, before);
+    }
+
+    // 颜色不合法时什么都不改变 也没有需要撤销的东西
+    #[test]
+    fn failed_action_changes_nothing() {
+        let mut canvas = Canvas::new(4, 1);
+        let before = canvas.clone();
+        assert!(canvas.execute_undoable(&Action::ChangeColor(0, 0, 256)).is_err());
+        assert_eq!(canvas, before);
+    }
+}
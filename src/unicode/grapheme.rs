@@ -0,0 +1,102 @@
+use super::lookup;
+use super::tables::GRAPHEME_BREAK;
+
+// 字素簇(grapheme cluster)就是用户眼中的"一个字"
+// 一个char只是一个unicode码点 而 "é" 可以是 'e' + '\u{301}' 两个码点 "👍🏽" 是 '👍' + 肤色修饰符 两个码点
+// 家庭emoji "👨‍👩‍👧" 甚至是用零宽连接符(ZWJ)连起来的5个码点 它们在屏幕上都只显示为一个字
+// 这里按 UAX #29 的扩展字素簇规则实现分界
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphemeCat {
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+    ExtendedPictographic,
+    Other,
+}
+
+const HANGUL_BASE: u32 = 0xAC00;
+const HANGUL_COUNT: u32 = 11172;
+const HANGUL_T_COUNT: u32 = 28;
+
+pub fn grapheme_cat(c: char) -> GraphemeCat {
+    let cp = c as u32;
+    // 韩文音节按算法判断 没有尾音的是LV 有尾音的是LVT
+    if (HANGUL_BASE..HANGUL_BASE + HANGUL_COUNT).contains(&cp) {
+        return if (cp - HANGUL_BASE).is_multiple_of(HANGUL_T_COUNT) { GraphemeCat::Lv } else { GraphemeCat::Lvt };
+    }
+    lookup(GRAPHEME_BREAK, c).unwrap_or(GraphemeCat::Other)
+}
+
+// 迭代字符串中的字素簇 返回的是对原字符串的切片
+pub struct Graphemes<'a> {
+    text: &'a str,
+}
+
+pub fn graphemes(s: &str) -> Graphemes<'_> {
+    Graphemes { text: s }
+}
+
+// 在 prev 和 next 之间是否可以断开 ri_count 是 prev 之前(含prev)连续的区域指示符个数
+// emoji_zwj 表示 prev 是 "扩展象形字 Extend* ZWJ" 序列的结尾
+fn is_boundary(prev: GraphemeCat, next: GraphemeCat, ri_count: usize, emoji_zwj: bool) -> bool {
+    use GraphemeCat::*;
+    match (prev, next) {
+        // GB3 GB4 GB5 回车换行是一个整体 控制字符前后都断开
+        (Cr, Lf) => false,
+        (Cr | Lf | Control, _) | (_, Cr | Lf | Control) => true,
+        // GB6 GB7 GB8 韩文字母组合成音节
+        (L, L | V | Lv | Lvt) | (Lv | V, V | T) | (Lvt | T, T) => false,
+        // GB9 GB9a GB9b 组合标记附着在前一个字符上
+        (_, Extend | Zwj | SpacingMark) | (Prepend, _) => false,
+        // GB11 emoji通过ZWJ连接成一个整体
+        (Zwj, ExtendedPictographic) if emoji_zwj => false,
+        // GB12 GB13 两个区域指示符组成一面国旗
+        (RegionalIndicator, RegionalIndicator) => ri_count.is_multiple_of(2),
+        // GB999
+        _ => true,
+    }
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let mut chars = self.text.char_indices();
+        let (_, first) = chars.next()?;
+        let mut prev = grapheme_cat(first);
+        let mut ri_count = usize::from(prev == GraphemeCat::RegionalIndicator);
+        // 是否处在 ExtendedPictographic Extend* 序列中
+        let mut in_emoji = prev == GraphemeCat::ExtendedPictographic;
+        let mut emoji_zwj = false;
+        let mut end = self.text.len();
+        for (i, c) in chars {
+            let next = grapheme_cat(c);
+            if is_boundary(prev, next, ri_count, emoji_zwj) {
+                end = i;
+                break;
+            }
+            emoji_zwj = in_emoji && next == GraphemeCat::Zwj;
+            in_emoji = match next {
+                GraphemeCat::ExtendedPictographic => true,
+                GraphemeCat::Extend => in_emoji,
+                _ => false,
+            };
+            ri_count = if next == GraphemeCat::RegionalIndicator { ri_count + 1 } else { 0 };
+            prev = next;
+        }
+        let (cluster, rest) = self.text.split_at(end);
+        self.text = rest;
+        Some(cluster)
+    }
+}
@@ -0,0 +1,119 @@
+// unicode 相关的工具 解释为什么一个"字"可能由多个char组成
+// grapheme: 字素簇分界  width: 终端显示宽度  normalize: NFC/NFD 规范化
+// 这些功能在标准库中都没有提供 实际项目中一般使用 unicode-segmentation、unicode-width、unicode-normalization 这几个库
+
+mod grapheme;
+mod normalize;
+mod tables;
+mod width;
+
+use std::cmp::Ordering;
+
+pub use grapheme::{grapheme_cat, graphemes, GraphemeCat};
+pub use normalize::{combining_class, is_nfc, nfc, nfd};
+pub use width::{char_width, pad, str_width, Align};
+
+// tables.rs 中的表都是按码点排好序、互不重叠的闭区间 用二分查找找到包含c的区间
+fn range_cmp(lo: char, hi: char, c: char) -> Ordering {
+    if hi < c {
+        Ordering::Less
+    } else if lo > c {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
+
+// 返回包含c的区间对应的属性值 没有区间包含c时返回None
+fn lookup<T: Copy>(table: &[(char, char, T)], c: char) -> Option<T> {
+    table.binary_search_by(|&(lo, hi, _)| range_cmp(lo, hi, c)).ok().map(|i| table[i].2)
+}
+
+// 没有属性值的区间表 只判断c是否在某个区间中
+fn in_ranges(table: &[(char, char)], c: char) -> bool {
+    table.binary_search_by(|&(lo, hi)| range_cmp(lo, hi, c)).is_ok()
+}
+
+pub fn demo() {
+    // 一个"字"可能由多个char组成
+    for s in ["张", "é", "e\u{301}", "👍🏽", "👨\u{200D}👩\u{200D}👧", "🇨🇳", "한"] {
+        println!(
+            "{:?}: {}个字节 {}个char {}个字素簇 显示宽度{}",
+            s,
+            s.len(),
+            s.chars().count(),
+            graphemes(s).count(),
+            str_width(s)
+        );
+    }
+
+    // 规范化 两种写法看起来一样 但只有规范化之后才相等
+    let composed = "caf\u{E9}";
+    let decomposed = "cafe\u{301}";
+    println!("{:?} == {:?} 结果是 {}", composed, decomposed, composed == decomposed);
+    println!("is_nfc: {} {}", is_nfc(composed), is_nfc(decomposed));
+    // 组合标记的组合类和字素簇分界属性 决定了它和前面的字符连在一起
+    let extend = grapheme_cat('\u{301}') == GraphemeCat::Extend;
+    println!("U+0301 组合类{} 分界属性是Extend: {}", combining_class('\u{301}'), extend);
+    println!("nfc({:?}) == {:?} 结果是 {}", decomposed, composed, nfc(decomposed) == composed);
+    println!("nfd(\"한\") 有{}个char", nfd("한").chars().count());
+
+    // 宽度: 用format!的{:<8}对齐时汉字会错位 按显示宽度填充才能对齐
+    println!("'字' 宽度{:?} 'a' 宽度{:?} '\\n' 宽度{:?}", char_width('字'), char_width('a'), char_width('\n'));
+    let rows = [("字符", "'张'", 4), ("字节", "b'a'", 1), ("emoji", "'😻'", 4), ("日文", "'の'", 4)];
+    println!("+{}+{}+{}+", "-".repeat(10), "-".repeat(8), "-".repeat(6));
+    for (name, example, size) in rows {
+        println!(
+            "|{}|{}|{}|",
+            pad(name, 10, Align::Left),
+            pad(example, 8, Align::Center),
+            pad(&size.to_string(), 6, Align::Right)
+        );
+    }
+    println!("+{}+{}+{}+", "-".repeat(10), "-".repeat(8), "-".repeat(6));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_graphemes() {
+        assert_eq!(graphemes("a👨\u{200D}👩\u{200D}👧b").collect::<Vec<_>>(), ["a", "👨\u{200D}👩\u{200D}👧", "b"]);
+        assert_eq!(graphemes("🇨🇳🇺🇸🇯").count(), 3); // 两面国旗 + 落单的区域指示符
+        assert_eq!(graphemes("\r\n\n").count(), 2);
+        assert_eq!(graphemes("\u{1100}\u{1161}\u{11A8}").count(), 1); // 韩文字母组合成一个音节
+        assert_eq!(grapheme_cat('\u{301}'), GraphemeCat::Extend);
+        assert_eq!(grapheme_cat('a'), GraphemeCat::Other);
+    }
+
+    #[test]
+    fn normalizes_nfc_and_nfd() {
+        let composed = "caf\u{E9}";
+        let decomposed = "cafe\u{301}";
+        assert_ne!(composed, decomposed);
+        assert_eq!(nfc(decomposed), composed);
+        assert_eq!(nfd(composed), decomposed);
+        assert!(is_nfc(composed) && !is_nfc(decomposed));
+        // 组合标记按组合类重新排序: 下加点(220) 排在 锐音符(230) 前面
+        assert_eq!(nfd("a\u{301}\u{323}"), "a\u{323}\u{301}");
+        assert_eq!(combining_class('\u{323}'), 220);
+        assert_eq!(combining_class('a'), 0);
+        assert_eq!(nfc("\u{1E0B}\u{323}"), "\u{1E0D}\u{307}");
+        assert_eq!(nfc("\u{1112}\u{1161}\u{11AB}"), "한");
+        assert_eq!(nfd("한").chars().count(), 3);
+        // Å(U+212B 埃米符号)是组合排除字符 NFC之后变成 U+00C5
+        assert_eq!(nfc("\u{212B}"), "\u{C5}");
+    }
+
+    #[test]
+    fn measures_display_width() {
+        assert_eq!(char_width('字'), Some(2));
+        assert_eq!(char_width('a'), Some(1));
+        assert_eq!(char_width('\u{301}'), Some(0));
+        assert_eq!(char_width('\n'), None);
+        assert_eq!(str_width("张三abc"), 7);
+        assert_eq!(pad("字符", 6, Align::Right), "  字符");
+        assert_eq!(pad("ab", 5, Align::Center), " ab  ");
+    }
+}
@@ -0,0 +1,123 @@
+use super::lookup;
+use super::tables::{CANONICAL_COMPOSITION, CANONICAL_DECOMPOSITION, COMBINING_CLASS};
+
+// 规范化 同一个 "é" 可以写成一个预组合字符 U+00E9 也可以写成 'e' + U+0301
+// 两者显示完全相同 但 == 比较的结果是false 比较或者存储之前需要统一成同一种形式
+// NFD: 完全分解成基础字符+组合标记  NFC: 先分解 再尽可能组合成预组合字符
+
+// 韩文音节由初声(L)、中声(V)和可选的终声(T)按公式组合而成 不需要查表
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+// 规范组合类 0表示起始字符(starter)
+pub fn combining_class(c: char) -> u8 {
+    lookup(COMBINING_CLASS, c).unwrap_or(0)
+}
+
+fn decompose_char(c: char, out: &mut Vec<char>) {
+    let s_index = (c as u32).wrapping_sub(S_BASE);
+    if s_index < S_COUNT {
+        let l = L_BASE + s_index / N_COUNT;
+        let v = V_BASE + (s_index % N_COUNT) / T_COUNT;
+        let t = T_BASE + s_index % T_COUNT;
+        // 这些都是合法的韩文字母码点 不会失败
+        out.extend([l, v].iter().filter_map(|&cp| char::from_u32(cp)));
+        if t != T_BASE {
+            out.extend(char::from_u32(t));
+        }
+        return;
+    }
+    match CANONICAL_DECOMPOSITION.binary_search_by_key(&c, |&(k, _)| k) {
+        // 分解结果本身还可能继续分解 例如 ǖ => ü + ̄ => u + ̈ + ̄
+        Ok(i) => CANONICAL_DECOMPOSITION[i].1.iter().for_each(|&d| decompose_char(d, out)),
+        Err(_) => out.push(c),
+    }
+}
+
+// 规范排序 相邻的非起始字符按组合类稳定排序 这样 a + ̣ + ́ 和 a + ́ + ̣ 会得到同样的结果
+fn canonical_order(chars: &mut [char]) {
+    let mut start = 0;
+    while start < chars.len() {
+        if combining_class(chars[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let end = chars[start..]
+            .iter()
+            .position(|&c| combining_class(c) == 0)
+            .map_or(chars.len(), |p| start + p);
+        // sort_by_key 是稳定排序 同组合类的字符保持原来的顺序
+        chars[start..end].sort_by_key(|&c| combining_class(c));
+        start = end;
+    }
+}
+
+fn compose_pair(a: char, b: char) -> Option<char> {
+    let (a_cp, b_cp) = (a as u32, b as u32);
+    // L + V => LV
+    if (L_BASE..L_BASE + L_COUNT).contains(&a_cp) && (V_BASE..V_BASE + V_COUNT).contains(&b_cp) {
+        return char::from_u32(S_BASE + ((a_cp - L_BASE) * V_COUNT + (b_cp - V_BASE)) * T_COUNT);
+    }
+    // LV + T => LVT
+    let s_index = a_cp.wrapping_sub(S_BASE);
+    if s_index < S_COUNT && s_index % T_COUNT == 0 && (T_BASE + 1..T_BASE + T_COUNT).contains(&b_cp) {
+        return char::from_u32(a_cp + (b_cp - T_BASE));
+    }
+    CANONICAL_COMPOSITION
+        .binary_search_by_key(&(a, b), |&(pair, _)| pair)
+        .ok()
+        .map(|i| CANONICAL_COMPOSITION[i].1)
+}
+
+fn nfd_chars(s: &str) -> Vec<char> {
+    let mut out = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        decompose_char(c, &mut out);
+    }
+    canonical_order(&mut out);
+    out
+}
+
+pub fn nfd(s: &str) -> String {
+    nfd_chars(s).into_iter().collect()
+}
+
+pub fn nfc(s: &str) -> String {
+    let mut out: Vec<char> = Vec::new();
+    // 最近一个起始字符在out中的位置
+    let mut starter: Option<usize> = None;
+    // 起始字符之后最后一个未被组合掉的字符的组合类 None表示紧挨着起始字符
+    let mut last_class: Option<u8> = None;
+    for c in nfd_chars(s) {
+        let class = combining_class(c);
+        if let Some(si) = starter {
+            // 中间隔着组合类不小于当前字符的标记时 当前字符被"阻挡" 不能和起始字符组合
+            let blocked = matches!(last_class, Some(last) if last >= class);
+            if !blocked {
+                if let Some(composed) = compose_pair(out[si], c) {
+                    out[si] = composed;
+                    continue;
+                }
+            }
+        }
+        if class == 0 {
+            starter = Some(out.len());
+            last_class = None;
+        } else {
+            last_class = Some(class);
+        }
+        out.push(c);
+    }
+    out.into_iter().collect()
+}
+
+pub fn is_nfc(s: &str) -> bool {
+    nfc(s) == s
+}
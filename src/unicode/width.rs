@@ -0,0 +1,58 @@
+use super::grapheme::{grapheme_cat, graphemes, GraphemeCat};
+use super::in_ranges;
+use super::tables::{WIDE, ZERO_WIDTH};
+
+// 终端显示宽度 大部分终端里汉字、假名、全角符号和emoji占两列 ASCII占一列 组合标记不占列
+// 所以 "字符".len() 是6(字节) chars().count() 是2 但显示宽度是4 对齐表格时应该用显示宽度
+
+// 单个字符的显示宽度 控制字符没有宽度 返回None
+pub fn char_width(c: char) -> Option<usize> {
+    if c == '\0' {
+        Some(0)
+    } else if c.is_control() {
+        None
+    } else if in_ranges(ZERO_WIDTH, c) {
+        Some(0)
+    } else if in_ranges(WIDE, c) {
+        Some(2)
+    } else {
+        Some(1)
+    }
+}
+
+// 一个字素簇的宽度取决于它的基础字符 后面跟着的组合标记不占宽度
+// 例外是 U+FE0F(emoji表示选择符) 它会把 ❤ 这类默认窄显示的符号变成两列宽的emoji
+// 两个区域指示符组成的国旗也显示为两列
+fn cluster_width(cluster: &str) -> usize {
+    let base = cluster.chars().map(|c| char_width(c).unwrap_or(0)).find(|&w| w > 0).unwrap_or(0);
+    let is_flag = cluster.chars().count() == 2
+        && cluster.chars().all(|c| grapheme_cat(c) == GraphemeCat::RegionalIndicator);
+    if base == 1 && (is_flag || cluster.contains('\u{FE0F}')) {
+        2
+    } else {
+        base
+    }
+}
+
+// 整个字符串的显示宽度 控制字符按0计算
+pub fn str_width(s: &str) -> usize {
+    graphemes(s).map(cluster_width).sum()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+// 按显示宽度填充空格 format!("{:<10}") 是按字符数填充的 遇到汉字就会错位
+pub fn pad(s: &str, width: usize, align: Align) -> String {
+    let fill = width.saturating_sub(str_width(s));
+    let (left, right) = match align {
+        Align::Left => (0, fill),
+        Align::Right => (fill, 0),
+        Align::Center => (fill / 2, fill - fill / 2),
+    };
+    format!("{}{}{}", " ".repeat(left), s, " ".repeat(right))
+}
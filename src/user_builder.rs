@@ -0,0 +1,254 @@
+use std::fmt;
+
+use crate::User;
+
+// 带校验的User构造 build_user 接受任意字符串 这里在构造时就检查用户名和邮箱是否合法
+// 用户名: 2到20个字符 以字母开头(包括中文等其他文字) 只能包含字母、数字、_、-、.
+// 邮箱: local@domain 规则是RFC 5322的常用子集 不支持带引号的local部分和IP地址形式的domain
+
+const USERNAME_MIN: usize = 2;
+const USERNAME_MAX: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserError {
+    // 必填字段没有设置
+    Missing(&'static str),
+    UsernameLength { len: usize },
+    // 用户名的第index个字符不允许出现
+    UsernameChar { ch: char, index: usize },
+    UsernameStart(char),
+    InvalidEmail { email: String, reason: &'static str },
+}
+
+impl UserError {
+    // 出错的字段 方便在表单中把错误显示在对应的输入框旁边
+    pub fn field(&self) -> &'static str {
+        match self {
+            UserError::Missing(field) => field,
+            UserError::UsernameLength { .. } | UserError::UsernameChar { .. } | UserError::UsernameStart(_) => "username",
+            UserError::InvalidEmail { .. } => "email",
+        }
+    }
+}
+
+impl fmt::Display for UserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.field())?;
+        match self {
+            UserError::Missing(_) => write!(f, "不能为空"),
+            UserError::UsernameLength { len } => {
+                write!(f, "长度必须在{}到{}个字符之间 实际为{}", USERNAME_MIN, USERNAME_MAX, len)
+            }
+            UserError::UsernameChar { ch, index } => write!(f, "第{}个字符 {:?} 不允许出现", index + 1, ch),
+            UserError::UsernameStart(ch) => write!(f, "必须以字母开头 而不是 {:?}", ch),
+            UserError::InvalidEmail { email, reason } => write!(f, "{} 不是合法的邮箱 {}", email, reason),
+        }
+    }
+}
+
+impl std::error::Error for UserError {}
+
+pub fn validate_username(username: &str) -> Result<(), UserError> {
+    let len = username.chars().count();
+    if len == 0 {
+        return Err(UserError::Missing("username"));
+    }
+    if !(USERNAME_MIN..=USERNAME_MAX).contains(&len) {
+        return Err(UserError::UsernameLength { len });
+    }
+    // is_alphabetic 对中文、日文等文字同样返回true 所以 "张三" 是合法的用户名
+    for (index, ch) in username.chars().enumerate() {
+        if index == 0 && !ch.is_alphabetic() {
+            return Err(UserError::UsernameStart(ch));
+        }
+        if !(ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.')) {
+            return Err(UserError::UsernameChar { ch, index });
+        }
+    }
+    Ok(())
+}
+
+fn email_error(email: &str, reason: &'static str) -> UserError {
+    UserError::InvalidEmail { email: email.to_string(), reason }
+}
+
+pub fn validate_email(email: &str) -> Result<(), UserError> {
+    if email.is_empty() {
+        return Err(UserError::Missing("email"));
+    }
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return Err(email_error(email, "缺少@"));
+    };
+    if local.contains('@') {
+        return Err(email_error(email, "只能有一个@"));
+    }
+    if local.is_empty() || local.len() > 64 {
+        return Err(email_error(email, "@前面的部分长度必须在1到64之间"));
+    }
+    if !local.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~-.".contains(c)) {
+        return Err(email_error(email, "@前面的部分包含不允许的字符"));
+    }
+    if local.starts_with('.') || local.ends_with('.') || local.contains("..") {
+        return Err(email_error(email, "@前面的部分不能以.开头或结尾 也不能有连续的."));
+    }
+    if domain.is_empty() || domain.len() > 253 {
+        return Err(email_error(email, "域名长度必须在1到253之间"));
+    }
+    let labels: Vec<&str> = domain.split('.').collect();
+    if labels.len() < 2 {
+        return Err(email_error(email, "域名至少需要两段 例如 example.com"));
+    }
+    for label in &labels {
+        if label.is_empty() || label.len() > 63 {
+            return Err(email_error(email, "域名的每一段长度必须在1到63之间"));
+        }
+        if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') || label.starts_with('-') || label.ends_with('-') {
+            return Err(email_error(email, "域名只能包含字母、数字和- 且-不能在开头或结尾"));
+        }
+    }
+    let tld = labels[labels.len() - 1];
+    if tld.len() < 2 || !tld.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(email_error(email, "顶级域名必须是至少两个字母"));
+    }
+    Ok(())
+}
+
+pub struct UserBuilder {
+    username: Option<String>,
+    email: Option<String>,
+    sign_in_count: u64,
+    active: bool,
+}
+
+impl UserBuilder {
+    // 默认值和 build_user 一致 登录次数为1 处于激活状态
+    pub fn new() -> UserBuilder {
+        UserBuilder { username: None, email: None, sign_in_count: 1, active: true }
+    }
+
+    pub fn username(mut self, username: impl Into<String>) -> UserBuilder {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn email(mut self, email: impl Into<String>) -> UserBuilder {
+        self.email = Some(email.into());
+        self
+    }
+
+    pub fn sign_in_count(mut self, sign_in_count: u64) -> UserBuilder {
+        self.sign_in_count = sign_in_count;
+        self
+    }
+
+    pub fn active(mut self, active: bool) -> UserBuilder {
+        self.active = active;
+        self
+    }
+
+    // 先检查用户名再检查邮箱 返回遇到的第一个错误
+    pub fn build(self) -> Result<User, UserError> {
+        let username = self.username.unwrap_or_default();
+        let email = self.email.unwrap_or_default();
+        validate_username(&username)?;
+        validate_email(&email)?;
+        Ok(User { username, email, sign_in_count: self.sign_in_count, active: self.active })
+    }
+}
+
+impl Default for UserBuilder {
+    fn default() -> Self {
+        UserBuilder::new()
+    }
+}
+
+impl User {
+    pub fn builder() -> UserBuilder {
+        UserBuilder::new()
+    }
+
+    // 代替 User { email, ..u1 } 其余字段从self复制 不会移动self中的username
+    // 只校验新的邮箱 用户名没有变化 校验规则收紧之前注册的旧用户名也能继续修改邮箱
+    pub fn with_email(&self, email: &str) -> Result<User, UserError> {
+        validate_email(email)?;
+        Ok(User { email: email.to_string(), ..self.clone() })
+    }
+}
+
+pub fn demo() {
+    match User::builder().username("张三").email("zhangsan@example.com").sign_in_count(3).active(false).build() {
+        Ok(user) => {
+            println!("{}", user);
+            match user.with_email("zhang.san+rust@mail.example.cn") {
+                Ok(copy) => println!("{} {}", user.username, copy),
+                Err(err) => println!("{}", err),
+            }
+            if let Err(err) = user.with_email("not-an-email") {
+                println!("{} (字段 {})", err, err.field());
+            }
+        }
+        Err(err) => println!("{}", err),
+    }
+    for (username, email) in [("1张三", "a@example.com"), ("zhangsan", "a@localhost"), ("Li_Si-2.0", "o'brien@ex-ample.co")] {
+        match User::builder().username(username).email(email).build() {
+            Ok(user) => println!("{}", user),
+            Err(err) => println!("{}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_with_defaults() {
+        let user = User::builder().username("张三").email("zhangsan@example.com").build().unwrap();
+        assert_eq!((user.sign_in_count, user.active), (1, true));
+        assert!(User::builder().username("Li_Si-2.0").email("o'brien@ex-ample.co").build().is_ok());
+    }
+
+    #[test]
+    fn reports_first_invalid_field() {
+        let cases = [
+            ("张", "a@example.com", UserError::UsernameLength { len: 1 }),
+            ("1张三", "a@example.com", UserError::UsernameStart('1')),
+            ("张 三", "a@example.com", UserError::UsernameChar { ch: ' ', index: 1 }),
+            ("", "a@example.com", UserError::Missing("username")),
+            ("zhangsan", "", UserError::Missing("email")),
+            ("zhangsan", "zhangsan.example.com", email_error("zhangsan.example.com", "缺少@")),
+            ("zhangsan", "a@b@example.com", email_error("a@b@example.com", "只能有一个@")),
+            ("zhangsan", ".a@example.com", email_error(".a@example.com", "@前面的部分不能以.开头或结尾 也不能有连续的.")),
+            ("zhangsan", "a@localhost", email_error("a@localhost", "域名至少需要两段 例如 example.com")),
+            ("zhangsan", "a@-x.com", email_error("a@-x.com", "域名只能包含字母、数字和- 且-不能在开头或结尾")),
+            ("zhangsan", "a@example.c0m", email_error("a@example.c0m", "顶级域名必须是至少两个字母")),
+            ("zhangsan", "张@example.com", email_error("张@example.com", "@前面的部分包含不允许的字符")),
+        ];
+        for (username, email, expected) in cases {
+            let err = User::builder().username(username).email(email).build().err();
+            assert_eq!(err.as_ref(), Some(&expected), "{} {}", username, email);
+        }
+        let err = User::builder().username("a".repeat(21)).email("a@example.com").build().err().unwrap();
+        assert_eq!(err, UserError::UsernameLength { len: 21 });
+        assert_eq!(err.field(), "username");
+    }
+
+    #[test]
+    fn with_email_copies_other_fields() {
+        let user = User::builder().username("张三").email("zhangsan@example.com").sign_in_count(3).active(false).build().unwrap();
+        let copy = user.with_email("zhang.san+rust@mail.example.cn").unwrap();
+        assert_eq!(copy.email, "zhang.san+rust@mail.example.cn");
+        assert_eq!((copy.username.as_str(), copy.sign_in_count, copy.active), ("张三", 3, false));
+        assert_eq!(user.email, "zhangsan@example.com");
+        assert_eq!(user.with_email("not-an-email").err().map(|e| e.field()), Some("email"));
+    }
+
+    // 用 build_user 创建的旧用户 用户名不满足现在的规则 仍然可以修改邮箱
+    #[test]
+    fn with_email_keeps_legacy_username() {
+        let legacy = User { username: String::from("张"), email: String::from("a@example.com"), sign_in_count: 7, active: true };
+        assert!(validate_username(&legacy.username).is_err());
+        let updated = legacy.with_email("b@example.com").unwrap();
+        assert_eq!((updated.username.as_str(), updated.email.as_str(), updated.sign_in_count), ("张", "b@example.com", 7));
+    }
+}
@@ -0,0 +1,483 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::atomic_file;
+use crate::user_builder::{validate_email, validate_username, UserError};
+use crate::User;
+
+// 用户注册表 在内存中保存一组User 并持久化到本地的CSV文件
+// 用户名和邮箱都必须唯一 邮箱比较时不区分大小写
+// 每次修改后整个文件重写一遍 先写到临时文件再rename 这样即使写到一半程序崩溃 原文件也不会损坏
+// 修改时先构造出新的记录并写入文件 写入成功之后才改动内存中的数据 写入失败时内存和文件保持一致
+
+const HEADER: &str = "username,email,sign_in_count,active";
+
+#[derive(Debug)]
+pub enum StoreError {
+    DuplicateUsername(String),
+    DuplicateEmail(String),
+    NotFound(String),
+    // 已停用的用户不能登录
+    Inactive(String),
+    // 用户名或邮箱没有通过校验
+    Invalid(UserError),
+    // 文件第line行(从1开始)格式不正确
+    Parse { line: usize, message: String },
+    Io(io::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::DuplicateUsername(name) => write!(f, "用户名 {} 已经存在", name),
+            StoreError::DuplicateEmail(email) => write!(f, "邮箱 {} 已经被注册", email),
+            StoreError::NotFound(key) => write!(f, "找不到用户 {}", key),
+            StoreError::Inactive(name) => write!(f, "用户 {} 已停用", name),
+            StoreError::Invalid(err) => write!(f, "用户信息不合法 {}", err),
+            StoreError::Parse { line, message } => write!(f, "第{}行格式错误: {}", line, message),
+            StoreError::Io(err) => write!(f, "读写文件失败: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<UserError> for StoreError {
+    fn from(err: UserError) -> StoreError {
+        StoreError::Invalid(err)
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(err: io::Error) -> StoreError {
+        StoreError::Io(err)
+    }
+}
+
+pub struct UserStore {
+    users: Vec<User>,
+    // None表示只保存在内存中
+    path: Option<PathBuf>,
+}
+
+impl UserStore {
+    pub fn new() -> UserStore {
+        UserStore { users: Vec::new(), path: None }
+    }
+
+    // 打开文件对应的注册表 文件不存在时从空表开始 第一次修改时创建文件
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<UserStore, StoreError> {
+        let path = path.as_ref().to_path_buf();
+        let users = match fs::read_to_string(&path) {
+            Ok(content) => parse_csv(&content)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let mut store = UserStore { users: Vec::new(), path: Some(path) };
+        // 逐个检查唯一性 防止手动编辑过的文件里有重复的用户
+        for (line, user) in users {
+            store
+                .check_unique(&user.username, &user.email, None)
+                .map_err(|err| StoreError::Parse { line, message: err.to_string() })?;
+            store.users.push(user);
+        }
+        Ok(store)
+    }
+
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    pub fn users(&self) -> impl Iterator<Item = &User> {
+        self.users.iter()
+    }
+
+    fn position(&self, username: &str) -> Result<usize, StoreError> {
+        self.users
+            .iter()
+            .position(|u| u.username == username)
+            .ok_or_else(|| StoreError::NotFound(username.to_string()))
+    }
+
+    // skip是正在被修改的那个用户 和自己比较时不算重复
+    fn check_unique(&self, username: &str, email: &str, skip: Option<usize>) -> Result<(), StoreError> {
+        for (i, user) in self.users.iter().enumerate() {
+            if Some(i) == skip {
+                continue;
+            }
+            if user.username == username {
+                return Err(StoreError::DuplicateUsername(username.to_string()));
+            }
+            if user.email.eq_ignore_ascii_case(email) {
+                return Err(StoreError::DuplicateEmail(email.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    // 把第i个用户换成user(i等于len时追加在最后)后写入文件 写入成功后再修改内存
+    fn commit(&mut self, i: usize, user: User) -> Result<(), StoreError> {
+        let users = self.users[..i].iter().chain([&user]).chain(self.users.iter().skip(i + 1));
+        self.write(&to_csv(users))?;
+        if i == self.users.len() {
+            self.users.push(user);
+        } else {
+            self.users[i] = user;
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, user: User) -> Result<(), StoreError> {
+        self.check_unique(&user.username, &user.email, None)?;
+        self.commit(self.users.len(), user)
+    }
+
+    // 校验后再添加 新用户默认是激活状态 登录次数为1
+    // add 不做校验 可以添加任意构造出来的User
+    pub fn register(&mut self, username: &str, email: &str) -> Result<(), StoreError> {
+        self.add(User::builder().username(username).email(email).build()?)
+    }
+
+    pub fn get(&self, username: &str) -> Option<&User> {
+        self.users.iter().find(|u| u.username == username)
+    }
+
+    pub fn find_by_email(&self, email: &str) -> Option<&User> {
+        self.users.iter().find(|u| u.email.eq_ignore_ascii_case(email))
+    }
+
+    // 按用户名或邮箱查找 包含@时按邮箱查找
+    pub fn lookup(&self, key: &str) -> Option<&User> {
+        if key.contains('@') {
+            self.find_by_email(key)
+        } else {
+            self.get(key)
+        }
+    }
+
+    // 下面的修改都用结构体更新语法构造新的User 没有改动的字段从旧的记录复制过来
+    // 新的邮箱和用户名和register一样需要通过校验
+    pub fn update_email(&mut self, username: &str, email: &str) -> Result<(), StoreError> {
+        validate_email(email)?;
+        let i = self.position(username)?;
+        self.check_unique(username, email, Some(i))?;
+        let old = &self.users[i];
+        let user = User { username: old.username.clone(), email: email.to_string(), ..*old };
+        self.commit(i, user)
+    }
+
+    pub fn rename(&mut self, username: &str, new_username: &str) -> Result<(), StoreError> {
+        validate_username(new_username)?;
+        let i = self.position(username)?;
+        let old = &self.users[i];
+        self.check_unique(new_username, &old.email, Some(i))?;
+        let user = User { username: new_username.to_string(), email: old.email.clone(), ..*old };
+        self.commit(i, user)
+    }
+
+    pub fn deactivate(&mut self, username: &str) -> Result<(), StoreError> {
+        let i = self.position(username)?;
+        let old = &self.users[i];
+        let user = User { username: old.username.clone(), email: old.email.clone(), active: false, ..*old };
+        self.commit(i, user)
+    }
+
+    // 登录一次 返回新的登录次数
+    pub fn sign_in(&mut self, username: &str) -> Result<u64, StoreError> {
+        let i = self.position(username)?;
+        let old = &self.users[i];
+        if !old.active {
+            return Err(StoreError::Inactive(username.to_string()));
+        }
+        let count = old.sign_in_count + 1;
+        let user = User { username: old.username.clone(), email: old.email.clone(), sign_in_count: count, ..*old };
+        self.commit(i, user)?;
+        Ok(count)
+    }
+
+    // 没有文件时只保存在内存中 写入方式见 atomic_file.rs
+    fn write(&self, content: &str) -> Result<(), StoreError> {
+        if let Some(path) = &self.path {
+            atomic_file::write(path, content.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for UserStore {
+    fn default() -> Self {
+        UserStore::new()
+    }
+}
+
+// CSV字段中出现逗号、引号或换行时 用双引号包起来 内部的引号写成两个引号
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn to_csv<'a>(users: impl IntoIterator<Item = &'a User>) -> String {
+    let mut out = String::from(HEADER);
+    out.push('\n');
+    for user in users {
+        out.push_str(&format!(
+            "{},{},{},{}\n",
+            quote(&user.username),
+            quote(&user.email),
+            user.sign_in_count,
+            user.active
+        ));
+    }
+    out
+}
+
+// 把CSV文本拆成记录 引号内的换行属于字段内容 返回每条记录和它开始的行号
+fn split_records(content: &str) -> Result<Vec<(usize, Vec<String>)>, StoreError> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut start_line = 1;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                fields.push(std::mem::take(&mut field));
+                records.push((start_line, std::mem::take(&mut fields)));
+                line += 1;
+                start_line = line;
+            }
+            _ => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if in_quotes {
+        return Err(StoreError::Parse { line: start_line, message: String::from("引号没有闭合") });
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((start_line, fields));
+    }
+    Ok(records)
+}
+
+// 返回每个用户和它的记录开始的行号 字段中有换行时记录会跨越多行 空行被跳过
+fn parse_csv(content: &str) -> Result<Vec<(usize, User)>, StoreError> {
+    let mut records = split_records(content)?.into_iter();
+    match records.next() {
+        Some((_, header)) if header.join(",") == HEADER => {}
+        Some((line, _)) => return Err(StoreError::Parse { line, message: format!("表头应该是 {}", HEADER) }),
+        None => return Ok(Vec::new()),
+    }
+    let mut users = Vec::new();
+    for (line, fields) in records {
+        if fields.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let [username, email, count, active] = <[String; 4]>::try_from(fields).map_err(|fields| {
+            StoreError::Parse { line, message: format!("应该有4个字段 实际有{}个", fields.len()) }
+        })?;
+        let sign_in_count = count
+            .parse()
+            .map_err(|_| StoreError::Parse { line, message: format!("登录次数 {} 不是整数", count) })?;
+        let active = active
+            .parse()
+            .map_err(|_| StoreError::Parse { line, message: format!("active {} 不是true或false", active) })?;
+        users.push((line, User { username, email, sign_in_count, active }));
+    }
+    Ok(users)
+}
+
+pub fn demo() {
+    let path = std::env::temp_dir().join(format!("myrust_users_{}.csv", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    if let Ok(mut store) = UserStore::open(&path) {
+        println!("打开新文件 is_empty = {}", store.is_empty());
+        let _ = store.register("张三", "zhangsan@example.com");
+        let _ = store.register("李四", "lisi@example.com");
+        let _ = store.sign_in("张三");
+        // 改成自己原来的邮箱不算重复 只是大小写不同
+        let _ = store.update_email("李四", "LISI@example.com");
+        // 用户名或邮箱重复时拒绝添加 邮箱不区分大小写
+        if let Err(err) = store.register("王五", "LiSi@example.com") {
+            println!("{}", err);
+        }
+        // 改名同样需要通过校验
+        if let Err(err) = store.rename("李四", "Li, \"Four\"") {
+            println!("{}", err);
+        }
+        let _ = store.rename("李四", "Li.Four");
+        let _ = store.deactivate("Li.Four");
+        if let Err(err) = store.sign_in("Li.Four") {
+            println!("{}", err);
+        }
+    }
+    // 重新打开文件 数据完整保留
+    if let Ok(reopened) = UserStore::open(&path) {
+        println!("重新打开后有{}个用户", reopened.len());
+        // 包含@时按邮箱查找 邮箱不区分大小写
+        if let Some(user) = reopened.lookup("lisi@example.com") {
+            println!("lisi@example.com 属于 {}", user.username);
+        }
+        for user in reopened.users() {
+            println!("{} <{}> 登录{}次 active = {}", user.username, user.email, user.sign_in_count, user.active);
+        }
+    }
+
+    // 格式错误的文件会报告行号
+    let _ = fs::write(&path, format!("{}\n张三,a@example.com,1,true\n李四,b@example.com,x,true\n", HEADER));
+    if let Err(err) = UserStore::open(&path) {
+        println!("{}", err);
+    }
+    let _ = fs::remove_file(&path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_user;
+
+    // 每个测试用自己的文件 测试是并行运行的
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("myrust_users_{}_{}.csv", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn persists_changes() {
+        let path = temp_path("persists");
+        let mut store = UserStore::open(&path).unwrap();
+        assert!(store.is_empty());
+        store.register("张三", "zhangsan@example.com").unwrap();
+        store.register("李四", "lisi@example.com").unwrap();
+        assert!(matches!(store.register("张三", "other@example.com"), Err(StoreError::DuplicateUsername(_))));
+        assert!(matches!(store.register("王五", "LiSi@example.com"), Err(StoreError::DuplicateEmail(_))));
+        assert!(matches!(store.register("王五", "wangwu@localhost"), Err(StoreError::Invalid(_))));
+        assert_eq!(store.len(), 2);
+
+        assert_eq!(store.sign_in("张三").unwrap(), 2);
+        assert_eq!(store.sign_in("张三").unwrap(), 3);
+        assert!(matches!(store.sign_in("王五"), Err(StoreError::NotFound(_))));
+        // 把邮箱改成自己原来的邮箱不算重复 改成别人的邮箱会失败
+        store.update_email("李四", "LISI@example.com").unwrap();
+        assert!(matches!(store.update_email("李四", "zhangsan@example.com"), Err(StoreError::DuplicateEmail(_))));
+        store.rename("李四", "Li.Four").unwrap();
+        store.deactivate("Li.Four").unwrap();
+        assert!(matches!(store.sign_in("Li.Four"), Err(StoreError::Inactive(_))));
+
+        let reopened = UserStore::open(&path).unwrap();
+        assert_eq!(reopened.len(), 2);
+        let zhang = reopened.lookup("zhangsan@example.com").unwrap();
+        assert_eq!((zhang.username.as_str(), zhang.sign_in_count, zhang.active), ("张三", 3, true));
+        let li = reopened.lookup("Li.Four").unwrap();
+        assert_eq!((li.email.as_str(), li.active), ("LISI@example.com", false));
+        // 临时文件已经被rename掉了
+        assert!(!path.with_extension("csv.tmp").exists());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn validates_updates() {
+        let mut store = UserStore::default();
+        store.register("张三", "zhangsan@example.com").unwrap();
+        assert!(matches!(store.update_email("张三", "zhangsan@localhost"), Err(StoreError::Invalid(_))));
+        assert!(matches!(store.update_email("张三", ""), Err(StoreError::Invalid(UserError::Missing("email")))));
+        assert!(matches!(store.rename("张三", "1abc"), Err(StoreError::Invalid(UserError::UsernameStart('1')))));
+        assert!(matches!(store.rename("张三", "Li, Four"), Err(StoreError::Invalid(UserError::UsernameChar { .. }))));
+        let user = store.get("张三").unwrap();
+        assert_eq!(user.email, "zhangsan@example.com");
+    }
+
+    #[test]
+    fn quotes_fields_added_without_validation() {
+        let path = temp_path("quoted");
+        let mut store = UserStore::open(&path).unwrap();
+        // add 不做校验 需要加引号的字段也能原样保存
+        store.add(build_user(String::from("a@example.com"), String::from("Li, \"Four\"\n"))).unwrap();
+        let reopened = UserStore::open(&path).unwrap();
+        assert_eq!(reopened.lookup("a@example.com").map(|u| u.username.as_str()), Some("Li, \"Four\"\n"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn failed_save_leaves_memory_unchanged() {
+        // 目录不存在 打开时当作空表 但每次写入都会失败
+        let dir = temp_path("missing_dir");
+        let mut store = UserStore::open(dir.join("users.csv")).unwrap();
+        assert!(matches!(store.register("张三", "zhangsan@example.com"), Err(StoreError::Io(_))));
+        assert!(store.is_empty());
+
+        store.users.push(build_user(String::from("a@example.com"), String::from("张三")));
+        assert!(matches!(store.sign_in("张三"), Err(StoreError::Io(_))));
+        assert!(matches!(store.update_email("张三", "b@example.com"), Err(StoreError::Io(_))));
+        assert!(matches!(store.rename("张三", "李四"), Err(StoreError::Io(_))));
+        assert!(matches!(store.deactivate("张三"), Err(StoreError::Io(_))));
+        let user = store.get("张三").unwrap();
+        assert_eq!((user.email.as_str(), user.sign_in_count, user.active), ("a@example.com", 1, true));
+    }
+
+    #[test]
+    fn reports_parse_errors_with_line() {
+        let path = temp_path("parse");
+        fs::write(&path, format!("{}\n张三,a@example.com,1,true\n李四,b@example.com,x,true\n", HEADER)).unwrap();
+        assert!(matches!(UserStore::open(&path), Err(StoreError::Parse { line: 3, .. })));
+        fs::write(&path, format!("{}\n张三,a@example.com,1,true\n张三,b@example.com,1,true\n", HEADER)).unwrap();
+        assert!(matches!(UserStore::open(&path), Err(StoreError::Parse { line: 3, .. })));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reports_record_start_line() {
+        let path = temp_path("multiline");
+        // 第一条记录的用户名跨了两行 重复的用户从第4行开始
+        let content = format!("{}\n\"张\n三\",a@example.com,1,true\n\"张\n三\",b@example.com,1,true\n", HEADER);
+        fs::write(&path, content).unwrap();
+        assert!(matches!(UserStore::open(&path), Err(StoreError::Parse { line: 4, .. })));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn skips_blank_lines() {
+        let path = temp_path("blank");
+        let content = format!("{}\n张三,a@example.com,1,true\n\n  \n李四,b@example.com,2,false\n\n", HEADER);
+        fs::write(&path, content).unwrap();
+        let store = UserStore::open(&path).unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get("李四").map(|u| u.sign_in_count), Some(2));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn memory_only_store() {
+        let mut memory = UserStore::default();
+        memory.add(build_user(String::from("aaa@example.com"), String::from("王五"))).unwrap();
+        assert!(memory.get("王五").is_some());
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::User;
+
+// 手写 User 的常用特征 对照 #[derive] 自动生成的版本 看看derive到底生成了什么
+// derive生成的实现会逐个字段调用同一个特征 手写时可以按需要改变行为:
+// Debug 隐藏邮箱 PartialEq/Eq/Hash 只比较用户名(用户名在 UserStore 中是唯一的)
+
+// 和User字段完全相同 但使用derive 用来对比
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct DerivedUser {
+    pub username: String,
+    pub email: String,
+    pub sign_in_count: u64,
+    pub active: bool,
+}
+
+impl From<&User> for DerivedUser {
+    fn from(user: &User) -> DerivedUser {
+        DerivedUser {
+            username: user.username.clone(),
+            email: user.email.clone(),
+            sign_in_count: user.sign_in_count,
+            active: user.active,
+        }
+    }
+}
+
+// 邮箱只保留第一个字符和域名 zhangsan@example.com => z***@example.com
+pub fn redact_email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            let first: String = local.chars().take(1).collect();
+            format!("{}***@{}", first, domain)
+        }
+        None => String::from("***"),
+    }
+}
+
+// derive(Debug) 生成的代码就是用 debug_struct 依次添加每个字段
+// debug_struct 会自动处理 {:?} 和 {:#?}(多行缩进) 两种格式
+impl fmt::Debug for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("User")
+            .field("username", &self.username)
+            .field("email", &redact_email(&self.email))
+            .field("sign_in_count", &self.sign_in_count)
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+// Display 没有derive 因为面向用户的格式只能由我们自己决定
+impl fmt::Display for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} <{}>", self.username, self.email)?;
+        if !self.active {
+            write!(f, " (已停用)")?;
+        }
+        Ok(())
+    }
+}
+
+// derive(PartialEq) 比较所有字段 这里只比较用户名 所以同一个用户登录次数不同也算相等
+impl PartialEq for User {
+    fn eq(&self, other: &User) -> bool {
+        self.username == other.username
+    }
+}
+
+// Eq 没有方法 只是声明 a == a 一定成立(f64的NaN就不满足 所以f64只实现了PartialEq)
+impl Eq for User {}
+
+// Hash 必须和 PartialEq 保持一致: a == b 时 hash(a) 必须等于 hash(b)
+// 所以 PartialEq 只看用户名 Hash 也只能用用户名
+impl Hash for User {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.username.hash(state);
+    }
+}
+
+// derive(Clone) 对每个字段调用clone String会深拷贝
+impl Clone for User {
+    fn clone(&self) -> User {
+        User {
+            username: self.username.clone(),
+            email: self.email.clone(),
+            sign_in_count: self.sign_in_count,
+            active: self.active,
+        }
+    }
+}
+
+// derive(Default) 对每个字段调用 Default::default() 空字符串、0和false
+// clippy会提示这个实现可以derive 这里正是为了展示derive生成的代码
+#[allow(clippy::derivable_impls)]
+impl Default for User {
+    fn default() -> User {
+        User { username: String::default(), email: String::default(), sign_in_count: u64::default(), active: bool::default() }
+    }
+}
+
+pub fn demo() {
+    let user = match User::builder().username("张三").email("zhangsan@example.com").sign_in_count(3).build() {
+        Ok(user) => user,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+    let derived = DerivedUser::from(&user);
+
+    // Debug 除了邮箱被隐藏之外 和derive的格式完全一样
+    println!("{:?}\n{:?}", user, derived);
+    println!("{:#?}", user);
+    println!("{} {}", user, redact_email("invalid"));
+
+    // 同一个用户名 登录次数和状态不同 手写的PartialEq认为相等 derive的版本比较所有字段
+    let mut other = user.clone();
+    other.sign_in_count += 1;
+    other.active = false;
+    println!("{} == {}: {}", user, other, user == other);
+    println!("derive的版本: {}", derived == DerivedUser::from(&other));
+    println!("{:?}", DerivedUser::from(&User::default()));
+
+    // 按用户名去重
+    let set: HashSet<User> = [user, other].into_iter().collect();
+    println!("去重后剩下{}个用户", set.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn zhangsan() -> User {
+        User::builder().username("张三").email("zhangsan@example.com").sign_in_count(3).build().unwrap()
+    }
+
+    // Debug 除了邮箱被隐藏之外 和derive的格式完全一样
+    #[test]
+    fn debug_matches_derive_except_email() {
+        let user = zhangsan();
+        let derived = DerivedUser::from(&user);
+        let expected = |s: String| s.replacen("DerivedUser", "User", 1).replace("zhangsan@example.com", "z***@example.com");
+        assert_eq!(format!("{:?}", user), expected(format!("{:?}", derived)));
+        assert_eq!(format!("{:#?}", user), expected(format!("{:#?}", derived)));
+        assert_eq!(format!("{:?}", user), r#"User { username: "张三", email: "z***@example.com", sign_in_count: 3, active: true }"#);
+        assert_eq!(redact_email("invalid"), "***");
+    }
+
+    #[test]
+    fn display_marks_inactive_users() {
+        let mut user = zhangsan();
+        assert_eq!(user.to_string(), "张三 <zhangsan@example.com>");
+        user.active = false;
+        assert_eq!(user.to_string(), "张三 <zhangsan@example.com> (已停用)");
+    }
+
+    // Clone 和 Default 与derive的结果逐字段一致
+    #[test]
+    fn clone_and_default_match_derive() {
+        let user = zhangsan();
+        assert_eq!(DerivedUser::from(&user.clone()), DerivedUser::from(&user));
+        assert_eq!(DerivedUser::from(&User::default()), DerivedUser::default());
+    }
+
+    // 相等的值hash必须相等 反过来不成立 所以不相等的值不检查hash
+    #[test]
+    fn eq_and_hash_use_only_username() {
+        let user = zhangsan();
+        let mut other = user.clone();
+        other.sign_in_count += 1;
+        other.active = false;
+        assert!(user == other);
+        assert_eq!(hash_of(&user), hash_of(&other));
+        // derive的版本比较所有字段 所以不相等
+        assert!(DerivedUser::from(&user) != DerivedUser::from(&other));
+        assert_eq!(hash_of(&DerivedUser::from(&user)), hash_of(&DerivedUser::from(&user.clone())));
+    }
+
+    // 按用户名去重
+    #[test]
+    fn hash_set_dedups_by_username() {
+        let user = zhangsan();
+        let mut other = user.clone();
+        other.sign_in_count += 1;
+        let lisi = User::builder().username("李四").email("lisi@example.com").build().unwrap();
+        let set: HashSet<User> = [user, other, lisi].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        let derived_set: HashSet<DerivedUser> = set.iter().map(DerivedUser::from).collect();
+        assert_eq!(derived_set.len(), 2);
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::Message;
+
+// 把 send_Msg 的例子改成多线程: 生产者线程通过mpsc通道发送Message 固定数量的工作线程处理
+// 工作线程共享同一个任务通道的接收端 Receiver不能clone 所以用Arc<Mutex<Receiver>>让它们轮流取任务
+// 收到Message::Quit后不再接收新消息 关闭任务通道 工作线程处理完已经取到的任务后退出 这就是优雅关闭
+// 每条消息按收到的顺序编号 结果先到的放进重排缓冲区 保证最终按编号顺序交回
+
+struct Job {
+    seq: usize,
+    msg: Message,
+}
+
+struct Done {
+    seq: usize,
+    output: String,
+}
+
+// 工作线程对每条消息做的处理 Quit不会交给工作线程
+pub fn process(msg: &Message) -> String {
+    match msg {
+        Message::Quit => String::from("quit"),
+        Message::Move { x, y } => format!("移动到 ({}, {}) 距离原点 {}", x, y, x.unsigned_abs() as u64 + y.unsigned_abs() as u64),
+        Message::Write(text) => format!("写入{}个字符: {}", text.chars().count(), text.to_uppercase()),
+        Message::ChangeColor(r, g, b) => {
+            let c = |v: &i32| (*v).clamp(0, 255);
+            format!("颜色 #{:02x}{:02x}{:02x}", c(r), c(g), c(b))
+        }
+    }
+}
+
+// 按编号重排 编号连续的结果才能交出去 前面的结果还没到时后面的先存起来
+struct Reorder<T> {
+    next: usize,
+    pending: BTreeMap<usize, T>,
+}
+
+impl<T> Reorder<T> {
+    fn new() -> Reorder<T> {
+        Reorder { next: 0, pending: BTreeMap::new() }
+    }
+
+    fn push(&mut self, seq: usize, value: T, out: &mut Vec<T>) {
+        self.pending.insert(seq, value);
+        while let Some(value) = self.pending.remove(&self.next) {
+            out.push(value);
+            self.next += 1;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Report {
+    // 按消息的接收顺序排列
+    pub outputs: Vec<String>,
+    // 每个工作线程处理的消息数
+    pub per_worker: Vec<usize>,
+    // 是因为Quit结束的 false表示生产者没有发Quit就断开了
+    pub quit: bool,
+    // 结果到达时 重排缓冲区中最多积压的结果数 说明结果确实是乱序到达的
+    pub max_buffered: usize,
+}
+
+// 用size个工作线程处理inbox中的消息 直到收到Quit或者所有发送端都被drop
+// size为0时没有线程处理任务 消息会一直积压 所以至少启动一个工作线程
+pub fn serve(size: usize, inbox: Receiver<Message>) -> Report {
+    let size = size.max(1);
+    let (job_tx, job_rx) = mpsc::channel::<Job>();
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let (done_tx, done_rx) = mpsc::channel::<Done>();
+
+    let workers: Vec<_> = (0..size)
+        .map(|_| {
+            let job_rx = Arc::clone(&job_rx);
+            let done_tx = done_tx.clone();
+            thread::spawn(move || {
+                let mut count = 0;
+                loop {
+                    // 锁只在recv期间持有 临时的MutexGuard在这条语句结束时就被释放 处理消息时其他线程可以取任务
+                    let job = job_rx.lock().unwrap().recv();
+                    let Ok(Job { seq, msg }) = job else {
+                        // 任务通道已关闭并且取空了
+                        return count;
+                    };
+                    done_tx.send(Done { seq, output: process(&msg) }).unwrap();
+                    count += 1;
+                }
+            })
+        })
+        .collect();
+    // 只留下工作线程中的发送端 它们都退出后done_rx的迭代才会结束
+    drop(done_tx);
+
+    // 重排在单独的线程中进行 一边分发一边收集结果
+    let collector = thread::spawn(move || {
+        let mut reorder = Reorder::new();
+        let mut outputs = Vec::new();
+        let mut max_buffered = 0;
+        for Done { seq, output } in done_rx {
+            reorder.push(seq, output, &mut outputs);
+            max_buffered = max_buffered.max(reorder.pending.len());
+        }
+        assert!(reorder.pending.is_empty(), "有结果丢失");
+        (outputs, max_buffered)
+    });
+
+    let mut quit = false;
+    for (seq, msg) in inbox.iter().enumerate() {
+        if msg == Message::Quit {
+            quit = true;
+            break;
+        }
+        job_tx.send(Job { seq, msg }).unwrap();
+    }
+    // 关闭任务通道 inbox也在这里被drop 生产者之后的send会返回Err
+    drop(job_tx);
+    drop(inbox);
+
+    let per_worker = workers.into_iter().map(|w| w.join().unwrap()).collect();
+    let (outputs, max_buffered) = collector.join().unwrap();
+    Report { outputs, per_worker, quit, max_buffered }
+}
+
+pub fn demo() {
+    let (tx, rx) = mpsc::channel();
+    let producer = thread::spawn(move || {
+        tx.send(Message::Write(String::from("hello"))).unwrap();
+        tx.send(Message::Move { x: 3, y: -4 }).unwrap();
+        tx.send(Message::ChangeColor(255, 128, 300)).unwrap();
+        tx.send(Message::Quit).unwrap();
+        // Quit之后服务端会关闭通道 这条消息要么被丢弃要么发送失败 不会被处理
+        let _ = tx.send(Message::Write(String::from("too late")));
+    });
+    let report = serve(2, rx);
+    producer.join().unwrap();
+    // 不管哪个线程先处理完 结果都按消息的接收顺序排列
+    for output in &report.outputs {
+        println!("{}", output);
+    }
+    println!(
+        "收到Quit = {} 每个工作线程处理的消息数 {:?} 重排缓冲区最多积压{}条",
+        report.quit, report.per_worker, report.max_buffered
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::time::Duration;
+
+    fn make_message(i: usize) -> Message {
+        let n = i as i32;
+        match i % 3 {
+            0 => Message::Move { x: n, y: -n / 2 },
+            1 => Message::Write(format!("msg-{}", i)),
+            _ => Message::ChangeColor(n % 300, n % 7 * 40, -n),
+        }
+    }
+
+    #[test]
+    fn stops_at_quit() {
+        let (tx, rx) = mpsc::channel();
+        let producer = thread::spawn(move || {
+            tx.send(Message::Write(String::from("hello"))).unwrap();
+            tx.send(Message::Move { x: 3, y: -4 }).unwrap();
+            tx.send(Message::ChangeColor(255, 128, 300)).unwrap();
+            tx.send(Message::Quit).unwrap();
+            let _ = tx.send(Message::Write(String::from("too late")));
+        });
+        let report = serve(2, rx);
+        producer.join().unwrap();
+        assert!(report.quit);
+        assert_eq!(report.outputs, ["写入5个字符: HELLO", "移动到 (3, -4) 距离原点 7", "颜色 #ff80ff"]);
+        assert_eq!(report.per_worker.len(), 2);
+    }
+
+    // 20000条消息 4个工作线程 没有消息丢失 结果按顺序 并且能在限定时间内关闭
+    #[test]
+    fn stress_keeps_order_and_shuts_down() {
+        const N: usize = 20_000;
+        let (tx, rx) = mpsc::channel();
+        let producer = thread::spawn(move || {
+            for i in 0..N {
+                tx.send(make_message(i)).unwrap();
+            }
+            tx.send(Message::Quit).unwrap();
+        });
+        let (report_tx, report_rx) = mpsc::channel();
+        thread::spawn(move || report_tx.send(serve(4, rx)).unwrap());
+        let report = match report_rx.recv_timeout(Duration::from_secs(30)) {
+            Ok(report) => report,
+            Err(RecvTimeoutError::Timeout) => panic!("工作线程池没有在30秒内关闭"),
+            Err(RecvTimeoutError::Disconnected) => panic!("工作线程池异常退出"),
+        };
+        producer.join().unwrap();
+        assert!(report.quit);
+        assert_eq!(report.outputs.len(), N);
+        assert_eq!(report.per_worker.iter().sum::<usize>(), N);
+        for (i, output) in report.outputs.iter().enumerate() {
+            assert_eq!(*output, process(&make_message(i)));
+        }
+    }
+
+    // 生产者没有发Quit就结束了 同样会正常关闭
+    #[test]
+    fn stops_when_producer_disconnects() {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for i in 0..10 {
+                tx.send(make_message(i)).unwrap();
+            }
+        });
+        let report = serve(3, rx);
+        assert!(!report.quit);
+        assert_eq!(report.outputs.len(), 10);
+    }
+
+    #[test]
+    fn zero_size_uses_one_worker() {
+        let (tx, rx) = mpsc::channel();
+        for i in 0..5 {
+            tx.send(make_message(i)).unwrap();
+        }
+        tx.send(Message::Quit).unwrap();
+        let report = serve(0, rx);
+        assert_eq!(report.per_worker, [5]);
+        assert_eq!(report.outputs.len(), 5);
+    }
+}